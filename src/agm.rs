@@ -0,0 +1,98 @@
+use gmp_mpfr_sys::{gmp, gmp::mpf_t};
+
+use crate::{WrappedMpf, WrappedMpfTri, allocate_mpf};
+
+// b = sqrt(a c), the expensive half of an AGM step
+async fn mpf_sqrt_mul(mut wrap: WrappedMpfTri) -> WrappedMpf {
+    unsafe {
+        gmp::mpf_mul(
+            &mut wrap.a as *mut mpf_t,
+            &wrap.b as *const mpf_t,
+            &wrap.c as *const mpf_t,
+        );
+        gmp::mpf_sqrt(&mut wrap.a as *mut mpf_t, &wrap.a as *const mpf_t);
+        WrappedMpf { a: wrap.a }
+    }
+}
+
+// Gauss-Legendre: a = 1, b = 1 / sqrt(2), t = 1 / 4, then repeatedly
+//   a' = (a + b) / 2, b' = sqrt(a b), t' = t - 2^k (a - a')^2
+// and pi = (a + b)^2 / (4 t). every step doubles the correct digits, so this is only
+// ~log2(digits) full precision square roots, against Chudnovsky's many small products
+pub async fn compute_pi(prec: u64) -> WrappedMpf {
+    // squaring a - b loses nothing, but the final t is a difference of close values
+    let prec = prec + 32;
+    unsafe {
+        let mut a = allocate_mpf(1, prec);
+        let mut b = allocate_mpf(2, prec);
+        gmp::mpf_sqrt(&mut b, &b);
+        gmp::mpf_ui_div(&mut b, 1, &b);
+        let mut t = allocate_mpf(1, prec);
+        gmp::mpf_div_2exp(&mut t, &t, 2);
+        let mut next_a = allocate_mpf(0, prec);
+        let mut diff = allocate_mpf(0, prec);
+        let mut k = 0;
+        loop {
+            let sqrt_handle = tokio::spawn(mpf_sqrt_mul(WrappedMpfTri {
+                a: allocate_mpf(0, prec),
+                b: a,
+                c: b,
+            }));
+            gmp::mpf_add(&mut next_a, &a, &b);
+            gmp::mpf_div_2exp(&mut next_a, &next_a, 1);
+            // t -= 2^k (a - a')^2
+            gmp::mpf_sub(&mut diff, &a, &next_a);
+            gmp::mpf_mul(&mut diff, &diff, &diff);
+            gmp::mpf_mul_2exp(&mut diff, &diff, k);
+            gmp::mpf_sub(&mut t, &t, &diff);
+            let mut next_b = sqrt_handle.await.unwrap().a;
+            gmp::mpf_swap(&mut a, &mut next_a);
+            gmp::mpf_swap(&mut b, &mut next_b);
+            gmp::mpf_clear(&mut next_b);
+            k += 1;
+            // the error after the last step is about (a - b)^2
+            gmp::mpf_sub(&mut diff, &a, &b);
+            let mut exp: i64 = 0;
+            gmp::mpf_get_d_2exp(&mut exp, &diff);
+            eprintln!("agm step {k}, a - b ~ 2^{exp}");
+            if gmp::mpf_sgn(&diff) == 0 || exp < -(prec as i64 / 2) {
+                break;
+            }
+        }
+        let mut pi = allocate_mpf(0, prec);
+        gmp::mpf_add(&mut pi, &a, &b);
+        gmp::mpf_mul(&mut pi, &pi, &pi);
+        gmp::mpf_mul_2exp(&mut t, &t, 2);
+        gmp::mpf_div(&mut pi, &pi, &t);
+        gmp::mpf_clear(&mut a);
+        gmp::mpf_clear(&mut b);
+        gmp::mpf_clear(&mut t);
+        gmp::mpf_clear(&mut next_a);
+        gmp::mpf_clear(&mut diff);
+        WrappedMpf { a: pi }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chudnovsky, make_cstr_mpf_exp};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn agrees_with_chudnovsky() {
+        for digits in [50, 300, 700] {
+            let prec = (digits as f64 * 10f64.log2()) as u64 + 64;
+            let mut agm = compute_pi(prec).await.a;
+            let mut chud = chudnovsky::compute_pi(digits, prec).await.a;
+            assert_eq!(
+                make_cstr_mpf_exp(agm, digits as usize, 10),
+                make_cstr_mpf_exp(chud, digits as usize, 10),
+                "{digits} digits"
+            );
+            unsafe {
+                gmp::mpf_clear(&mut agm);
+                gmp::mpf_clear(&mut chud);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use gmp_mpfr_sys::{gmp, gmp::mpf_t, gmp::mpz_t};
+use std::ffi::CString;
+
+use crate::{WrappedMpf, allocate_mpf, allocate_mpz};
+
+// a non-negative integer or rational "a" / "a/b", of any size
+unsafe fn parse_rational(s: &str, prec: u64) -> mpf_t {
+    let (num, den) = s.split_once('/').unwrap_or((s, "1"));
+    unsafe {
+        let mut value = allocate_mpf(0, prec);
+        let mut tmp = allocate_mpf(0, prec);
+        let mut z: mpz_t = allocate_mpz(0);
+        for (i, part) in [num, den].iter().enumerate() {
+            let cstr = CString::new(*part).unwrap();
+            assert!(
+                gmp::mpz_set_str(&mut z, cstr.as_ptr(), 10) == 0 && gmp::mpz_sgn(&z) >= 0,
+                "bad number {part}"
+            );
+            if i == 0 {
+                gmp::mpf_set_z(&mut value, &z);
+            } else {
+                assert!(gmp::mpz_sgn(&z) > 0, "zero denominator");
+                gmp::mpf_set_z(&mut tmp, &z);
+                gmp::mpf_div(&mut value, &value, &tmp);
+            }
+        }
+        gmp::mpz_clear(&mut z);
+        gmp::mpf_clear(&mut tmp);
+        value
+    }
+}
+
+// a^(1/r) for a > 0. newton on the inverse root, x += x (1 - a x^r) / r, which
+// needs no division, and each step doubles the correct bits so every step but the
+// last runs at a fraction of the final precision
+unsafe fn root(a: &mpf_t, r: u32, prec: u64) -> mpf_t {
+    unsafe {
+        // a = d 2^e with e a multiple of r, so the double guess can't overflow
+        let mut e: i64 = 0;
+        let mut d = gmp::mpf_get_d_2exp(&mut e, a);
+        while e.rem_euclid(r as i64) != 0 {
+            d *= 2.0;
+            e -= 1;
+        }
+        let mut x = allocate_mpf(0, prec);
+        gmp::mpf_set_d(&mut x, d.powf(-1.0 / r as f64));
+        let shift = e / r as i64;
+        if shift > 0 {
+            gmp::mpf_div_2exp(&mut x, &x, shift as u64);
+        } else {
+            gmp::mpf_mul_2exp(&mut x, &x, shift.unsigned_abs());
+        }
+
+        // precisions from the top down, halving until a double's worth is left
+        let mut precs = vec![prec];
+        while *precs.last().unwrap() > 100 {
+            precs.push(precs.last().unwrap() / 2 + 16);
+        }
+        let mut a_p = allocate_mpf(0, prec);
+        let mut err = allocate_mpf(0, prec);
+        for &p in precs.iter().rev() {
+            gmp::mpf_set_prec(&mut x, p);
+            gmp::mpf_set_prec(&mut a_p, p);
+            gmp::mpf_set_prec(&mut err, p);
+            gmp::mpf_set(&mut a_p, a);
+            // err = 1 - a x^r
+            gmp::mpf_pow_ui(&mut err, &x, r as u64);
+            gmp::mpf_mul(&mut err, &err, &a_p);
+            gmp::mpf_ui_sub(&mut err, 1, &err);
+            gmp::mpf_mul(&mut err, &err, &x);
+            gmp::mpf_div_ui(&mut err, &err, r as u64);
+            gmp::mpf_add(&mut x, &x, &err);
+        }
+
+        // a^(1/r) = a x^(r - 1)
+        let mut value = allocate_mpf(0, prec);
+        gmp::mpf_pow_ui(&mut value, &x, (r - 1) as u64);
+        gmp::mpf_mul(&mut value, &value, a);
+        gmp::mpf_clear(&mut x);
+        gmp::mpf_clear(&mut a_p);
+        gmp::mpf_clear(&mut err);
+        value
+    }
+}
+
+// the r-th root of a non-negative integer or rational given as "a" or "a/b"
+pub async fn compute_root(value: &str, r: u32, prec: u64) -> WrappedMpf {
+    unsafe {
+        let mut a = parse_rational(value, prec);
+        if gmp::mpf_sgn(&a) == 0 {
+            return WrappedMpf { a };
+        }
+        let root = root(&a, r, prec);
+        gmp::mpf_clear(&mut a);
+        WrappedMpf { a: root }
+    }
+}
+
+// (1 + sqrt(5)) / 2
+pub async fn compute_phi(prec: u64) -> WrappedMpf {
+    let mut phi = compute_root("5", 2, prec).await;
+    unsafe {
+        gmp::mpf_add_ui(&mut phi.a, &phi.a, 1);
+        gmp::mpf_div_2exp(&mut phi.a, &phi.a, 1);
+    }
+    phi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{make_cstr_mpf_exp, with_point};
+
+    fn parsed(s: &str) -> String {
+        unsafe {
+            let mut v = parse_rational(s, 256);
+            let (digits, exp) = make_cstr_mpf_exp(v, 30, 10);
+            gmp::mpf_clear(&mut v);
+            with_point(&digits, exp)
+        }
+    }
+
+    #[test]
+    fn parses_integers_and_fractions() {
+        assert_eq!(parsed("2"), "2");
+        assert_eq!(parsed("0"), "0");
+        assert_eq!(parsed("1/4"), "0.25");
+        assert_eq!(parsed("10/4"), "2.5");
+        assert_eq!(parsed("1/3"), "0.333333333333333333333333333333");
+        assert_eq!(
+            parsed("123456789012345678901234567890"),
+            "123456789012345678901234567890"
+        );
+        assert_eq!(parsed("1/1000"), "0.001");
+    }
+
+    #[test]
+    #[should_panic(expected = "zero denominator")]
+    fn rejects_zero_denominator() {
+        parsed("1/0");
+    }
+
+    #[test]
+    #[should_panic(expected = "bad number")]
+    fn rejects_negative_numbers() {
+        parsed("-2");
+    }
+}
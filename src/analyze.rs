@@ -0,0 +1,488 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+
+use crate::manifest::json_str;
+use crate::search::IN_FLIGHT;
+use crate::source::{CHUNK, Source};
+use crate::{compute, flag_value, make_cstr_mpf, prec_for_digits};
+
+const DEFAULT_WINDOW: usize = 100_000;
+
+// a maximal run of one digit
+#[derive(Clone, Copy)]
+struct Run {
+    digit: u8,
+    len: u64,
+    pos: u64,
+}
+
+struct Window {
+    start: u64,
+    digits: u64,
+    counts: [u64; 10],
+}
+
+// what one chunk contributes. runs touching either end of the chunk can carry on
+// into its neighbours, so they are kept apart from the histogram of the rest
+struct Stats {
+    counts: [u64; 10],
+    pairs: [u64; 100],
+    runs: Vec<u64>,
+    longest: Option<Run>,
+    first: Run,
+    last: Run,
+    windows: Vec<Window>,
+}
+
+fn add_run(hist: &mut Vec<u64>, longest: &mut Option<Run>, run: Run) {
+    if hist.len() <= run.len as usize {
+        hist.resize(run.len as usize + 1, 0);
+    }
+    hist[run.len as usize] += 1;
+    // ties go to the earlier run, runs are added in order
+    if longest.is_none_or(|l| run.len > l.len) {
+        *longest = Some(run);
+    }
+}
+
+fn chunk_stats(block: &[u8], start: u64, window: usize) -> Stats {
+    let mut s = Stats {
+        counts: [0; 10],
+        pairs: [0; 100],
+        runs: Vec::new(),
+        longest: None,
+        first: Run {
+            digit: block[0] - b'0',
+            len: 0,
+            pos: start,
+        },
+        last: Run {
+            digit: 0,
+            len: 0,
+            pos: 0,
+        },
+        windows: Vec::new(),
+    };
+    let mut cur = s.first;
+    let mut seen_first = false;
+    for (i, &d) in block.iter().enumerate() {
+        assert!(d.is_ascii_digit(), "analyze only supports decimal digits");
+        let d = d - b'0';
+        s.counts[d as usize] += 1;
+        if i > 0 {
+            s.pairs[(block[i - 1] - b'0') as usize * 10 + d as usize] += 1;
+        }
+        if d == cur.digit {
+            cur.len += 1;
+            continue;
+        }
+        if seen_first {
+            add_run(&mut s.runs, &mut s.longest, cur);
+        } else {
+            s.first = cur;
+            seen_first = true;
+        }
+        cur = Run {
+            digit: d,
+            len: 1,
+            pos: start + i as u64,
+        };
+    }
+    // a chunk that is all one run has first and last the same
+    if !seen_first {
+        s.first = cur;
+    }
+    s.last = cur;
+    for (i, w) in block.chunks(window).enumerate() {
+        let mut counts = [0u64; 10];
+        for &d in w {
+            counts[(d - b'0') as usize] += 1;
+        }
+        s.windows.push(Window {
+            start: start + (i * window) as u64,
+            digits: w.len() as u64,
+            counts,
+        });
+    }
+    s
+}
+
+// the chunk statistics stitched together in order
+struct Totals {
+    digits: u64,
+    counts: [u64; 10],
+    pairs: [u64; 100],
+    runs: Vec<u64>,
+    longest: Option<Run>,
+    // the run still going at the end of what has been added so far
+    open: Option<Run>,
+    windows: Vec<Window>,
+}
+
+impl Totals {
+    fn new() -> Totals {
+        Totals {
+            digits: 0,
+            counts: [0; 10],
+            pairs: [0; 100],
+            runs: Vec::new(),
+            longest: None,
+            open: None,
+            windows: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, s: Stats) {
+        if let Some(open) = self.open {
+            self.pairs[open.digit as usize * 10 + s.first.digit as usize] += 1;
+        }
+        for d in 0..10 {
+            self.counts[d] += s.counts[d];
+            self.digits += s.counts[d];
+        }
+        for (p, n) in self.pairs.iter_mut().zip(s.pairs.iter()) {
+            *p += n;
+        }
+        let first = match self.open.take() {
+            Some(open) if open.digit == s.first.digit => Run {
+                len: open.len + s.first.len,
+                ..open
+            },
+            Some(open) => {
+                add_run(&mut self.runs, &mut self.longest, open);
+                s.first
+            }
+            None => s.first,
+        };
+        let n: u64 = s.counts.iter().sum();
+        if s.first.len == n {
+            self.open = Some(first);
+        } else {
+            add_run(&mut self.runs, &mut self.longest, first);
+            if self.runs.len() < s.runs.len() {
+                self.runs.resize(s.runs.len(), 0);
+            }
+            for (r, n) in self.runs.iter_mut().zip(s.runs.iter()) {
+                *r += n;
+            }
+            if let Some(l) = s.longest
+                && self.longest.is_none_or(|cur| l.len > cur.len)
+            {
+                self.longest = Some(l);
+            }
+            self.open = Some(s.last);
+        }
+        self.windows.extend(s.windows);
+    }
+
+    fn finish(&mut self) {
+        if let Some(open) = self.open.take() {
+            add_run(&mut self.runs, &mut self.longest, open);
+        }
+    }
+}
+
+// ln gamma(a) for a a positive multiple of 1/2, which is all chi-square needs
+fn ln_gamma_half(a: f64) -> f64 {
+    let (mut x, mut acc) = if a.fract() == 0.0 {
+        (1.0, 0.0)
+    } else {
+        (0.5, 0.5 * std::f64::consts::PI.ln())
+    };
+    while x < a {
+        acc += x.ln();
+        x += 1.0;
+    }
+    acc
+}
+
+// upper regularized incomplete gamma Q(a, x), by the series below a + 1 and the
+// continued fraction above it
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let scale = (-x + a * x.ln() - ln_gamma_half(a)).exp();
+    if x < a + 1.0 {
+        let (mut sum, mut term, mut ap) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..10_000 {
+            ap += 1.0;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        return (1.0 - sum * scale).max(0.0);
+    }
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let (mut c, mut d) = (1.0 / tiny, 1.0 / b);
+    let mut h = d;
+    for i in 1..10_000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    scale * h
+}
+
+// chance of a chi-square this big or bigger with df degrees of freedom
+fn chi_square_p(chi: f64, df: u32) -> f64 {
+    gamma_q(df as f64 / 2.0, chi / 2.0)
+}
+
+// against the uniform distribution
+fn chi_square(counts: &[u64]) -> f64 {
+    let n: u64 = counts.iter().sum();
+    let expected = n as f64 / counts.len() as f64;
+    counts
+        .iter()
+        .map(|&c| (c as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+fn list(values: impl Iterator<Item = String>) -> String {
+    format!("[{}]", values.collect::<Vec<_>>().join(", "))
+}
+
+fn object(fields: &[(&str, String)], indent: &str) -> String {
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(k, v)| format!("{indent}  {}: {v}", json_str(k)))
+        .collect();
+    format!("{{\n{}\n{indent}}}", body.join(",\n"))
+}
+
+fn report(t: &Totals, source: &str, window: usize, seconds: f64) -> String {
+    let chi = chi_square(&t.counts);
+    // overlapping pairs aren't independent, so the pair test is Good's serial test:
+    // the pair statistic less the single digit one, chi-square with 90 degrees of
+    // freedom
+    let serial = chi_square(&t.pairs) - chi;
+    let longest = t.longest.map_or("null".to_string(), |l| {
+        format!(
+            "{{\"digit\": {}, \"length\": {}, \"position\": {}}}",
+            l.digit, l.len, l.pos
+        )
+    });
+    let runs = list(
+        t.runs
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 0)
+            .map(|(len, n)| format!("[{len}, {n}]")),
+    );
+    let windows: Vec<(u64, u64, f64, f64)> = t
+        .windows
+        .iter()
+        .map(|w| {
+            let sum: u64 = (0..10).map(|d| d as u64 * w.counts[d]).sum();
+            let chi = chi_square(&w.counts);
+            (w.start, w.digits, chi, sum as f64 / w.digits as f64)
+        })
+        .collect();
+    let window_list: Vec<String> = windows.iter().map(|(start, digits, chi, mean)| {
+        format!(
+            "      {{\"start\": {start}, \"digits\": {digits}, \"chi_square\": {chi:.6}, \"p_value\": {:.6}, \"mean\": {mean:.6}}}",
+            chi_square_p(*chi, 9)
+        )
+    }).collect();
+    let chis = windows.iter().map(|w| w.2);
+    let window_fields = [
+        ("size", window.to_string()),
+        ("count", windows.len().to_string()),
+        (
+            "chi_square_min",
+            format!("{:.6}", chis.clone().fold(f64::INFINITY, f64::min)),
+        ),
+        (
+            "chi_square_max",
+            format!("{:.6}", chis.clone().fold(0.0, f64::max)),
+        ),
+        (
+            "chi_square_mean",
+            format!("{:.6}", chis.clone().sum::<f64>() / windows.len() as f64),
+        ),
+        (
+            "p_below_0.01",
+            chis.filter(|c| chi_square_p(*c, 9) < 0.01)
+                .count()
+                .to_string(),
+        ),
+        ("windows", format!("[\n{}\n    ]", window_list.join(",\n"))),
+    ];
+    let fields = [
+        ("source", json_str(source)),
+        ("digits", t.digits.to_string()),
+        ("counts", list(t.counts.iter().map(u64::to_string))),
+        ("expected", format!("{:.1}", t.digits as f64 / 10.0)),
+        ("chi_square", format!("{chi:.6}")),
+        ("chi_square_df", "9".to_string()),
+        ("p_value", format!("{:.6}", chi_square_p(chi, 9))),
+        (
+            "pairs",
+            list(
+                t.pairs
+                    .chunks(10)
+                    .map(|row| list(row.iter().map(u64::to_string))),
+            ),
+        ),
+        ("serial_chi_square", format!("{serial:.6}")),
+        ("serial_df", "90".to_string()),
+        ("serial_p_value", format!("{:.6}", chi_square_p(serial, 90))),
+        ("run_lengths", runs),
+        ("longest_run", longest),
+        ("windows", object(&window_fields, "  ")),
+        ("seconds", format!("{seconds:.6}")),
+    ];
+    object(&fields, "")
+}
+
+// each chunk is analysed in its own task, then the results are stitched together in
+// order. chunks are whole windows, so no window straddles two
+async fn analyze_stream(
+    mut next_chunk: impl FnMut(&mut Vec<u8>),
+    start: u64,
+    window: usize,
+) -> Totals {
+    let mut pending: VecDeque<JoinHandle<Stats>> = VecDeque::new();
+    let mut totals = Totals::new();
+    let mut pos = start;
+    loop {
+        let mut block = Vec::new();
+        next_chunk(&mut block);
+        if block.is_empty() {
+            break;
+        }
+        let base = pos;
+        pos += block.len() as u64;
+        pending.push_back(tokio::spawn(
+            async move { chunk_stats(&block, base, window) },
+        ));
+        if pending.len() >= IN_FLIGHT {
+            totals.add(pending.pop_front().unwrap().await.unwrap());
+        }
+    }
+    for handle in pending {
+        totals.add(handle.await.unwrap());
+    }
+    totals.finish();
+    totals
+}
+
+// pi-thing analyze [--file <file> | --digits <n> [--constant <name>]] [--window <w>]
+//                   [--output <file>]
+// digit frequencies, pairs, runs and per window frequencies as a json report. as in
+// search, positions count from the first digit of the output
+pub async fn run(args: &[String]) {
+    let window = flag_value(args, "--window").map_or(DEFAULT_WINDOW, |w| w.parse().unwrap());
+    assert!(window > 0, "empty window");
+    let chunk = window * (CHUNK / window).max(1);
+    let start = Instant::now();
+    let (source, totals) = match flag_value(args, "--file") {
+        Some(path) => {
+            let mut source = Source::open(path);
+            let offset = source.offset;
+            let next = |buf: &mut Vec<u8>| source.read_chunk(buf, chunk);
+            (path.to_string(), analyze_stream(next, offset, window).await)
+        }
+        None => {
+            let digits = flag_value(args, "--digits")
+                .expect("analyze needs --file or --digits")
+                .parse::<u64>()
+                .unwrap();
+            let constant = flag_value(args, "--constant").unwrap_or("pi");
+            eprintln!("Computing {digits} digits of {constant}");
+            let value = compute(constant, "chudnovsky", digits, prec_for_digits(digits)).await;
+            let printout = make_cstr_mpf(value, digits as usize);
+            // the rounded last digit is left out
+            let mut chunks = printout.as_bytes()[..printout.len() - 1].chunks(chunk);
+            let next = |buf: &mut Vec<u8>| {
+                buf.clear();
+                buf.extend_from_slice(chunks.next().unwrap_or_default());
+            };
+            (constant.to_string(), analyze_stream(next, 0, window).await)
+        }
+    };
+    assert!(totals.digits > 0, "no digits to analyze");
+    let json = report(&totals, &source, window, start.elapsed().as_secs_f64());
+    match flag_value(args, "--output") {
+        Some(path) => std::fs::write(path, json + "\n").unwrap(),
+        None => println!("{json}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn chunks_stitch_into_the_whole() {
+        // long runs, some spanning several chunks, one at each end
+        let mut digits = b"7777".to_vec();
+        for i in 0..400u64 {
+            let d = b'0' + (i * 7 % 10) as u8;
+            digits.extend(std::iter::repeat_n(d, (i % 5 * i % 23 + 1) as usize));
+        }
+        digits.extend_from_slice(b"33333333333");
+
+        // runs counted directly
+        let mut runs = Vec::new();
+        let mut longest = (0u8, 0u64, 0u64);
+        let mut i = 0;
+        while i < digits.len() {
+            let j = i + digits[i..].iter().take_while(|&&d| d == digits[i]).count();
+            let len = (j - i) as u64;
+            if runs.len() <= len as usize {
+                runs.resize(len as usize + 1, 0);
+            }
+            runs[len as usize] += 1;
+            if len > longest.1 {
+                longest = (digits[i] - b'0', len, 10 + i as u64);
+            }
+            i = j;
+        }
+        let mut pairs = [0u64; 100];
+        for w in digits.windows(2) {
+            pairs[(w[0] - b'0') as usize * 10 + (w[1] - b'0') as usize] += 1;
+        }
+
+        for (window, per_chunk) in [(1, 1), (3, 1), (4, 5), (10, 7), (digits.len(), 1)] {
+            let mut chunks = digits.chunks(window * per_chunk);
+            let next = |buf: &mut Vec<u8>| {
+                buf.clear();
+                buf.extend_from_slice(chunks.next().unwrap_or_default());
+            };
+            let t = analyze_stream(next, 10, window).await;
+            assert_eq!(t.digits, digits.len() as u64);
+            for d in 0..10u8 {
+                let n = digits.iter().filter(|&&c| c == b'0' + d).count() as u64;
+                assert_eq!(t.counts[d as usize], n);
+            }
+            assert_eq!(t.pairs, pairs, "pairs, window {window}");
+            assert_eq!(t.runs, runs, "runs, window {window}");
+            let l = t.longest.unwrap();
+            assert_eq!((l.digit, l.len, l.pos), longest, "longest, window {window}");
+            assert_eq!(t.windows.len(), digits.len().div_ceil(window));
+            for (i, w) in t.windows.iter().enumerate() {
+                assert_eq!(w.start, 10 + (i * window) as u64);
+                assert_eq!(w.digits, digits[i * window..].len().min(window) as u64);
+            }
+        }
+    }
+}
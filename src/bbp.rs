@@ -0,0 +1,100 @@
+use crate::flag_value;
+use crate::modarith::pow_mod;
+
+// terms per spawned task
+const CHUNK: u64 = 1 << 16;
+// the fractions are 128 bit and every term adds at most one ulp of error, so even
+// 2^40 terms leave 22 good hex digits
+const MAX_COUNT: usize = 20;
+
+// r / m as a 128 bit fraction of one, for r < m
+pub fn frac(r: u64, m: u64) -> u128 {
+    let hi = ((r as u128) << 64) / m as u128;
+    let rem = ((r as u128) << 64) % m as u128;
+    let lo = (rem << 64) / m as u128;
+    (hi << 64) | lo
+}
+
+// the fractional part of sum_{k in k1..k2} 16^(n - k) / (8k + j), all k <= n. only the
+// fractional part of each term matters, which is 16^(n - k) mod (8k + j) / (8k + j)
+fn head_sum(n: u64, j: u64, k1: u64, k2: u64) -> u128 {
+    (k1..k2).fold(0u128, |acc, k| {
+        let m = 8 * k + j;
+        acc.wrapping_add(frac(pow_mod(16, n - k, m), m))
+    })
+}
+
+// sum_{k > n} 16^(n - k) / (8k + j), until the terms drop below the last bit
+fn tail_sum(n: u64, j: u64) -> u128 {
+    (1..32).fold(0u128, |acc, d| {
+        acc.wrapping_add((u128::MAX / (8 * (n + d) + j) as u128) >> (4 * d))
+    })
+}
+
+// pi = sum_k 16^-k (4 / (8k + 1) - 2 / (8k + 4) - 1 / (8k + 5) - 1 / (8k + 6)), so the
+// fractional part of 16^n pi only needs each term mod 1, which is a modular power.
+// wrapping arithmetic on the 128 bit fractions is exactly arithmetic mod 1
+async fn pi_frac(n: u64) -> u128 {
+    let handles: Vec<_> = (0..=n)
+        .step_by(CHUNK as usize)
+        .map(|k1| {
+            let k2 = (k1 + CHUNK).min(n + 1);
+            tokio::spawn(async move {
+                [(1, 4u128), (4, 2), (5, 1), (6, 1)]
+                    .iter()
+                    .fold(0u128, |acc, &(j, c)| {
+                        let s = head_sum(n, j, k1, k2).wrapping_mul(c);
+                        if j == 1 {
+                            acc.wrapping_add(s)
+                        } else {
+                            acc.wrapping_sub(s)
+                        }
+                    })
+            })
+        })
+        .collect();
+    let mut total = 4u128
+        .wrapping_mul(tail_sum(n, 1))
+        .wrapping_sub(2u128.wrapping_mul(tail_sum(n, 4)))
+        .wrapping_sub(tail_sum(n, 5))
+        .wrapping_sub(tail_sum(n, 6));
+    for handle in handles {
+        total = total.wrapping_add(handle.await.unwrap());
+    }
+    total
+}
+
+// pi-thing bbp --position <n> [--count <k>]: k hex digits of pi starting at the n-th
+// hex digit after the point, without computing any of the digits before it
+pub async fn run(args: &[String]) {
+    let position = flag_value(args, "--position")
+        .expect("bbp needs --position")
+        .parse::<u64>()
+        .unwrap();
+    assert!(position >= 1, "positions start at 1");
+    let count = flag_value(args, "--count").map_or(16, |c| c.parse::<usize>().unwrap());
+    assert!(
+        count <= MAX_COUNT,
+        "at most {MAX_COUNT} digits are reliable"
+    );
+    println!("Computing hex digits of pi from position {position}");
+    let frac = pi_frac(position - 1).await;
+    let hex = format!("{frac:032X}");
+    println!("{}", &hex[..count]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn known_hex_digits() {
+        // pi = 3.243F6A8885A308D3...
+        assert_eq!(&format!("{:032X}", pi_frac(0).await)[..8], "243F6A88");
+        assert_eq!(&format!("{:032X}", pi_frac(8).await)[..8], "85A308D3");
+        assert_eq!(
+            &format!("{:032X}", pi_frac(999).await)[..12],
+            "349F1C09B075"
+        );
+    }
+}
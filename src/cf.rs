@@ -0,0 +1,201 @@
+use gmp_mpfr_sys::{gmp, gmp::mpf_t, gmp::mpz_t};
+use std::time::Instant;
+
+use crate::{allocate_mpf, allocate_mpz, compute, flag_value, make_cstr_mpz, prec_for_digits};
+
+// digits computed past the ones the expansion relies on, as in verify
+const GUARD_DIGITS: u64 = 10;
+const DEFAULT_CONVERGENTS: usize = 20;
+
+// the partial quotients shared by every number in [(n - w) / 2^k, (n + w) / 2^k]:
+// euclid on both ends at once, up to the first quotient they disagree on. past that
+// the digits can't tell which expansion is the right one, except that when the ends
+// are one apart there is a rational [.., max] between them. if its denominator is
+// below small, the value is taken to be that rational and the expansion ends with it:
+// an irrational that close to a fraction that simple would need a partial quotient
+// of more than 1 / (small^2 width) next
+unsafe fn shared_terms(
+    n: &mpz_t,
+    w: &mpz_t,
+    k: u64,
+    small: &mpz_t,
+    max_terms: usize,
+) -> (Vec<mpz_t>, bool) {
+    unsafe {
+        let (mut a, mut b) = (allocate_mpz(0), allocate_mpz(1));
+        let (mut c, mut d) = (allocate_mpz(0), allocate_mpz(1));
+        gmp::mpz_sub(&mut a, n, w);
+        gmp::mpz_add(&mut c, n, w);
+        gmp::mpz_mul_2exp(&mut b, &b, k);
+        gmp::mpz_mul_2exp(&mut d, &d, k);
+        let (mut q1, mut r1) = (allocate_mpz(0), allocate_mpz(0));
+        let (mut q2, mut r2) = (allocate_mpz(0), allocate_mpz(0));
+        // denominators of the last two convergents, from q_-1 = 0 and q_-2 = 1
+        let (mut den1, mut den2) = (allocate_mpz(0), allocate_mpz(1));
+        let mut terms = Vec::new();
+        let mut finite = false;
+        // a zero denominator is an end of the interval whose expansion has run out
+        while terms.len() < max_terms && gmp::mpz_sgn(&b) != 0 && gmp::mpz_sgn(&d) != 0 {
+            gmp::mpz_fdiv_qr(&mut q1, &mut r1, &a, &b);
+            gmp::mpz_fdiv_qr(&mut q2, &mut r2, &c, &d);
+            if gmp::mpz_cmp(&q1, &q2) != 0 {
+                // q1 is the larger one from here on
+                if gmp::mpz_cmp(&q1, &q2) < 0 {
+                    gmp::mpz_swap(&mut q1, &mut q2);
+                }
+                gmp::mpz_sub(&mut r1, &q1, &q2);
+                gmp::mpz_addmul(&mut den2, &q1, &den1);
+                if gmp::mpz_cmp_ui(&r1, 1) == 0 && gmp::mpz_cmp(&den2, small) < 0 {
+                    let mut term = allocate_mpz(0);
+                    gmp::mpz_set(&mut term, &q1);
+                    terms.push(term);
+                    finite = true;
+                }
+                break;
+            }
+            let mut term = allocate_mpz(0);
+            gmp::mpz_set(&mut term, &q1);
+            terms.push(term);
+            // (a, b) = (b, a mod b), the same for (c, d)
+            gmp::mpz_swap(&mut a, &mut b);
+            gmp::mpz_swap(&mut b, &mut r1);
+            gmp::mpz_swap(&mut c, &mut d);
+            gmp::mpz_swap(&mut d, &mut r2);
+            gmp::mpz_addmul(&mut den2, &q1, &den1);
+            gmp::mpz_swap(&mut den1, &mut den2);
+        }
+        for z in [
+            &mut a, &mut b, &mut c, &mut d, &mut q1, &mut r1, &mut q2, &mut r2, &mut den1,
+            &mut den2,
+        ] {
+            gmp::mpz_clear(z);
+        }
+        (terms, finite)
+    }
+}
+
+// the expansion of value, known to digits significant digits and held to prec bits
+unsafe fn expand(value: &mpf_t, digits: u64, prec: u64, max_terms: usize) -> (Vec<mpz_t>, bool) {
+    unsafe {
+        // digits counts significant digits, so the error taken for the value is
+        // 2^e 10^-digits, with 2^(e-1) <= |value| < 2^e. value * 2^k, truncated, keeps
+        // prec + 64 bits of it, enough to leave all the uncertainty to that error
+        let mut e = 0;
+        gmp::mpf_get_d_2exp(&mut e, value);
+        let k = u64::try_from(prec as i64 + 64 - e).expect("value too large for cf");
+        let mut scaled = allocate_mpf(0, prec);
+        gmp::mpf_mul_2exp(&mut scaled, value, k);
+        let mut n = allocate_mpz(0);
+        gmp::mpz_set_f(&mut n, &scaled);
+        // half width of the interval in units of 2^-k: 2^(k+e) / 10^digits, plus one
+        // for the truncation
+        let (mut w, mut ten) = (allocate_mpz(1), allocate_mpz(0));
+        gmp::mpz_ui_pow_ui(&mut ten, 10, digits);
+        gmp::mpz_mul_2exp(&mut w, &w, prec + 64);
+        gmp::mpz_cdiv_q(&mut w, &w, &ten);
+        gmp::mpz_add_ui(&mut w, &w, 1);
+        // fractions with denominators below 10^(digits / 4), far from what the digits
+        // can resolve, count as exact
+        let mut small = allocate_mpz(0);
+        gmp::mpz_ui_pow_ui(&mut small, 10, digits / 4);
+        let terms = shared_terms(&n, &w, k, &small, max_terms);
+        gmp::mpf_clear(&mut scaled);
+        for z in [&mut n, &mut w, &mut ten, &mut small] {
+            gmp::mpz_clear(z);
+        }
+        terms
+    }
+}
+
+// pi-thing cf --digits <n> [--constant <name>] [--terms <k>] [--convergents <k>]
+// the simple continued fraction of the constant, as far as n correct significant
+// digits pin it down, and its first convergents as exact fractions
+pub async fn run(args: &[String]) {
+    let digits = flag_value(args, "--digits")
+        .expect("cf needs --digits")
+        .parse::<u64>()
+        .unwrap();
+    let constant = flag_value(args, "--constant").unwrap_or("pi");
+    let max_terms = flag_value(args, "--terms").map_or(usize::MAX, |k| k.parse().unwrap());
+    let convergents = flag_value(args, "--convergents")
+        .map_or(DEFAULT_CONVERGENTS, |k| k.parse::<usize>().unwrap());
+    println!("Computing {digits} digits of {constant}");
+    let prec = prec_for_digits(digits + GUARD_DIGITS);
+    let value = compute(constant, "chudnovsky", digits + GUARD_DIGITS, prec).await;
+
+    let start = Instant::now();
+    let (terms, finite) = unsafe { expand(&value, digits, prec, max_terms) };
+    println!(
+        "{} terms from {digits} significant digits ({:.3}s)",
+        terms.len(),
+        start.elapsed().as_secs_f64()
+    );
+    let shown: Vec<String> = terms.iter().map(|t| make_cstr_mpz(*t)).collect();
+    match shown.split_first() {
+        None => println!("{digits} digits don't pin down the integer part"),
+        Some((a0, [])) => println!("[{a0}]"),
+        Some((a0, rest)) => println!("[{a0}; {}]", rest.join(", ")),
+    }
+    if finite {
+        println!("the value is rational as far as {digits} digits tell, so the expansion ends");
+    }
+
+    // p_i = a_i p_(i-1) + p_(i-2), the same for q, from p_-1 / q_-1 = 1 / 0 and
+    // p_-2 / q_-2 = 0 / 1
+    unsafe {
+        let (mut p1, mut p2) = (allocate_mpz(1), allocate_mpz(0));
+        let (mut q1, mut q2) = (allocate_mpz(0), allocate_mpz(1));
+        for (i, a) in terms.iter().take(convergents).enumerate() {
+            gmp::mpz_addmul(&mut p2, a, &p1);
+            gmp::mpz_addmul(&mut q2, a, &q1);
+            gmp::mpz_swap(&mut p1, &mut p2);
+            gmp::mpz_swap(&mut q1, &mut q2);
+            println!("{i}: {}/{}", make_cstr_mpz(p1), make_cstr_mpz(q1));
+        }
+        for z in [&mut p1, &mut p2, &mut q1, &mut q2] {
+            gmp::mpz_clear(z);
+        }
+        for mut t in terms {
+            gmp::mpz_clear(&mut t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn expansion(constant: &str, digits: u64) -> (Vec<String>, bool) {
+        let prec = prec_for_digits(digits + GUARD_DIGITS);
+        let mut value = compute(constant, "chudnovsky", digits + GUARD_DIGITS, prec).await;
+        unsafe {
+            let (terms, finite) = expand(&value, digits, prec, usize::MAX);
+            let shown = terms.iter().map(|t| make_cstr_mpz(*t)).collect();
+            for mut t in terms {
+                gmp::mpz_clear(&mut t);
+            }
+            gmp::mpf_clear(&mut value);
+            (shown, finite)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn known_expansions() {
+        let (pi, finite) = expansion("pi", 60).await;
+        assert_eq!(pi[..5], ["3", "7", "15", "1", "292"]);
+        assert!(!finite);
+        let (sqrt2, finite) = expansion("sqrt2", 60).await;
+        assert!(sqrt2.len() > 50 && sqrt2[1..].iter().all(|t| t == "2"));
+        assert!(!finite);
+        assert_eq!(expansion("sqrt4", 60).await, (vec!["2".to_string()], true));
+        assert_eq!(
+            expansion("sqrt1/4", 60).await,
+            (vec!["0".to_string(), "2".to_string()], true)
+        );
+        // newton leaves this just short of 3/2
+        assert_eq!(
+            expansion("cbrt27/8", 60).await,
+            (vec!["1".to_string(), "2".to_string()], true)
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+
+use crate::{compute, flag_value, make_cstr_mpf, prec_for_digits};
+
+// a known prefix of a constant's digits as mpf_get_str gives them (leading digit, no
+// point), with the digit right after it, which is decimal place `digits`
+struct Known {
+    constant: &'static str,
+    digits: usize,
+    sha256: &'static str,
+    next: u8,
+}
+
+const fn known(constant: &'static str, digits: usize, sha256: &'static str, next: u8) -> Known {
+    Known {
+        constant,
+        digits,
+        sha256,
+        next,
+    }
+}
+
+#[rustfmt::skip]
+const KNOWN: [Known; 12] = [
+    known("pi", 10, "0ac59a6eff4c0d73984b7ec775d6a01864e80dbc5e5488c594ed1ae4748ff56d", b'5'),
+    known("pi", 100, "7543f46cb30935724933c0ca91b28c1bdba6ab37185dc5dae886f78e614f329e", b'9'),
+    known("pi", 1000, "2f77ba99f311974f0d188c0b19710260c11c70d6f4d96d78570d4a59c3b0dbe0", b'9'),
+    known("pi", 10000, "2a32257c1b63c17b152835a29b8f832c1beb4d04d1594e18632104cf29243309", b'8'),
+    known("pi", 100000, "ad86ad5fd8620210bcb5785ed727acb572ed55b6089c65b9cc31960f728db38a", b'6'),
+    known("pi", 1000000, "387877db67fdddbde761c053c4376e0b411b10fd2b126fd8b1249963cb628877", b'1'),
+    known("e", 10, "fdb3c49f93e64fe2d4ef86cc50bd41c3cb645145f68681abb88716ac3f278228", b'4'),
+    known("e", 100, "d12ab333edd8a515d01bcc4a72389abc1e6eced08c5f80f3f3e152378c45ffa7", b'4'),
+    known("e", 1000, "e6c0ec97877f75493ef99fc7b0333399d535282319ed17ecbff1654606ef9aae", b'4'),
+    known("e", 10000, "6ead2a1c7d86d37b60e25a06825eaf01502cc340434e2828be4833ad6af2d4c8", b'8'),
+    known("e", 100000, "bc591b8e77ea26b70898c5a635f1cec9696b5ffde902b8a6916ba2fc98cb4ed6", b'8'),
+    known("e", 1000000, "40c99fe6a116a9843523fb3c8331792b092257608cdb1a748318055eab7ad1aa", b'8'),
+];
+
+// digits computed past the largest checked prefix, so the rounded last digit is
+// never one that gets checked
+const GUARD_DIGITS: usize = 10;
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// runs every known check the printout is long enough for. the last printed digit is
+// rounded, so a check needs at least one digit past its own. returns the number of
+// checks run and the ones that failed
+fn run_checks(constant: &str, printout: &str) -> (usize, Vec<String>) {
+    let mut failures = Vec::new();
+    let covered: Vec<&Known> = KNOWN
+        .iter()
+        .filter(|k| k.constant == constant && k.digits + 1 < printout.len())
+        .collect();
+    for k in covered.iter() {
+        if sha256_hex(&printout.as_bytes()[..k.digits]) != k.sha256 {
+            failures.push(format!("sha256 of the first {} digits", k.digits));
+        }
+        if printout.as_bytes()[k.digits] != k.next {
+            failures.push(format!("digit at decimal place {}", k.digits));
+        }
+    }
+    (covered.len(), failures)
+}
+
+// prints how a run's output compares to the known checks. returns false on a mismatch
+pub fn report(constant: &str, printout: &str) -> bool {
+    let (count, failures) = run_checks(constant, printout);
+    if count == 0 {
+        println!(
+            "known checks: none cover {} digits of {constant}",
+            printout.len()
+        );
+    } else if failures.is_empty() {
+        println!("known checks: all {count} match");
+    } else {
+        for f in failures.iter() {
+            println!("known checks: MISMATCH in {f}");
+        }
+    }
+    failures.is_empty()
+}
+
+// pi-thing selftest [--max-digits <n>]: computes every constant in the table at each
+// of its sizes up to n and checks the results
+pub async fn selftest(args: &[String]) {
+    let max_digits =
+        flag_value(args, "--max-digits").map_or(100000, |d| d.parse::<usize>().unwrap());
+    let mut ok = true;
+    for k in KNOWN.iter().filter(|k| k.digits <= max_digits) {
+        let digits = (k.digits + GUARD_DIGITS) as u64;
+        let start = Instant::now();
+        let value = compute(k.constant, "chudnovsky", digits, prec_for_digits(digits)).await;
+        let printout = make_cstr_mpf(value, digits as usize);
+        let (_, failures) = run_checks(k.constant, &printout);
+        println!(
+            "{} {} digits: {} ({:.3}s)",
+            k.constant,
+            k.digits,
+            if failures.is_empty() { "ok" } else { "FAILED" },
+            start.elapsed().as_secs_f64()
+        );
+        ok &= failures.is_empty();
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_of_known_vectors() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn short_prefixes_match_the_table() {
+        assert_eq!(run_checks("pi", "314159265358"), (1, vec![]));
+        assert_eq!(run_checks("e", "271828182845"), (1, vec![]));
+        assert_eq!(run_checks("pi", "3141592653"), (0, vec![]));
+        let (count, failures) = run_checks("pi", "314159265348");
+        assert_eq!(count, 1);
+        assert_eq!(failures, ["digit at decimal place 10"]);
+        let (_, failures) = run_checks("e", "271828182945");
+        assert_eq!(failures, ["sha256 of the first 10 digits"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn computed_digits_match_the_table() {
+        for constant in ["pi", "e"] {
+            let digits = 1000 + GUARD_DIGITS as u64;
+            let value = compute(constant, "chudnovsky", digits, prec_for_digits(digits)).await;
+            let printout = make_cstr_mpf(value, digits as usize);
+            assert_eq!(run_checks(constant, &printout), (3, vec![]), "{constant}");
+        }
+    }
+}
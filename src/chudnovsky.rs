@@ -0,0 +1,231 @@
+use gmp_mpfr_sys::{gmp, gmp::mpf_t, gmp::mpz_t};
+
+use crate::series::{PQT, Poly, Series, sum_series, terms_for_digits};
+use crate::{
+    WrappedMpf, WrappedMpfTri, WrappedMpz, WrappedMpzBi, allocate_mpf, allocate_mpz, mpf_cast,
+    mpf_mul, mpz_add_ns,
+};
+
+const A: u64 = 13591409;
+const B: u64 = 545140134;
+const C: u64 = 640320;
+const D: u64 = 426880;
+const E: u64 = 10005;
+const C3_24: u64 = C.pow(3) / 24;
+
+// 1 / pi = 12 / C^(3/2) * sum_{k >= 0} (-1)^k (6k)! (A + B k) / ((3k)! (k!)^3 C^3k)
+pub struct Chudnovsky;
+
+impl Series for Chudnovsky {
+    fn p(&self) -> Vec<Poly> {
+        // -(2k - 1)(6k - 1)(6k - 5)
+        vec![
+            Poly::new(&[-1]),
+            Poly::new(&[-1, 2]),
+            Poly::new(&[-1, 6]),
+            Poly::new(&[-5, 6]),
+        ]
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        // k^3 C^3 / 24
+        vec![
+            Poly::new(&[0, 1]),
+            Poly::new(&[0, 1]),
+            Poly::new(&[0, 1]),
+            Poly::new(&[C3_24 as i64]),
+        ]
+    }
+
+    fn a(&self) -> Poly {
+        Poly::new(&[A as i64, B as i64])
+    }
+}
+
+async fn calc_sqrt_pell(prec: u64) -> WrappedMpzBi {
+    unsafe {
+        let mut xy = WrappedMpzBi {
+            a: allocate_mpz(0),
+            b: allocate_mpz(0),
+        };
+        let mut p1 = WrappedMpzBi {
+            a: allocate_mpz(1u64),
+            b: allocate_mpz(0u64),
+        };
+        let mut p2 = WrappedMpzBi {
+            a: allocate_mpz(4001u64),
+            b: allocate_mpz(40u64),
+        };
+        let mut target = WrappedMpz {
+            a: allocate_mpz(10),
+        };
+        gmp::mpz_pow_ui(
+            &mut target.a as *mut mpz_t,
+            &target.a as *const mpz_t,
+            (prec / 2) + 5,
+        );
+        loop {
+            // x = x1*x2 + D*y1*y2
+            let x1_wrap = WrappedMpz { a: p1.a };
+            let x2_wrap = WrappedMpz { a: p2.a };
+            let x_1_c_handle = tokio::spawn(async {
+                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
+                let x1 = x1_wrap;
+                let x2 = x2_wrap;
+                gmp::mpz_mul(
+                    &mut tmp.a as *mut mpz_t,
+                    &x1.a as *const mpz_t,
+                    &x2.a as *const mpz_t,
+                );
+                WrappedMpz { a: tmp.a }
+            });
+            let y1_wrap = WrappedMpz { a: p1.b };
+            let y2_wrap = WrappedMpz { a: p2.b };
+            let x_2_c_handle = tokio::spawn(async {
+                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
+                let y1 = y1_wrap;
+                let y2 = y2_wrap;
+                gmp::mpz_mul(
+                    &mut tmp.a as *mut mpz_t,
+                    &y1.a as *const mpz_t,
+                    &y2.a as *const mpz_t,
+                );
+                gmp::mpz_mul_ui(&mut tmp.a as *mut mpz_t, &tmp.a as *const mpz_t, E);
+                WrappedMpz { a: tmp.a }
+            });
+
+            // y = x1*y2 + y1*x2
+            let x1_wrap = WrappedMpz { a: p1.a };
+            let y2_wrap = WrappedMpz { a: p2.b };
+            let y_1_c_handle = tokio::spawn(async {
+                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
+                let x1 = x1_wrap;
+                let y2 = y2_wrap;
+                gmp::mpz_mul(
+                    &mut tmp.a as *mut mpz_t,
+                    &x1.a as *const mpz_t,
+                    &y2.a as *const mpz_t,
+                );
+                WrappedMpz { a: tmp.a }
+            });
+            let y1_wrap = WrappedMpz { a: p1.b };
+            let x2_wrap = WrappedMpz { a: p2.a };
+            let y_2_c_handle = tokio::spawn(async {
+                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
+                let y1 = y1_wrap;
+                let x2 = x2_wrap;
+                gmp::mpz_mul(
+                    &mut tmp.a as *mut mpz_t,
+                    &y1.a as *const mpz_t,
+                    &x2.a as *const mpz_t,
+                );
+                WrappedMpz { a: tmp.a }
+            });
+            let x_handle = tokio::spawn(async move {
+                let (x_1_c, x_2_c) = tokio::join!(x_1_c_handle, x_2_c_handle);
+                let tmp = WrappedMpz { a: allocate_mpz(0) };
+                mpz_add_ns(WrappedMpz { a: tmp.a }, x_1_c.unwrap(), x_2_c.unwrap())
+            });
+            let y_handle = tokio::spawn(async move {
+                let (y_1_c, y_2_c) = tokio::join!(y_1_c_handle, y_2_c_handle);
+                let tmp = WrappedMpz { a: allocate_mpz(0) };
+                mpz_add_ns(WrappedMpz { a: tmp.a }, y_1_c.unwrap(), y_2_c.unwrap())
+            });
+            let (x_r, y_r) = tokio::join!(x_handle, y_handle);
+            gmp::mpz_set(&mut xy.a as *mut mpz_t, &x_r.unwrap().a as *const mpz_t);
+            gmp::mpz_set(&mut xy.b as *mut mpz_t, &y_r.unwrap().a as *const mpz_t);
+            if gmp::mpz_cmp(&xy.b as *const mpz_t, &target.a as *const mpz_t) > 0 {
+                // should dealloc memory here
+                break;
+            }
+            gmp::mpz_set(&mut p1.a as *mut mpz_t, &p2.a as *const mpz_t);
+            gmp::mpz_set(&mut p1.b as *mut mpz_t, &p2.b as *const mpz_t);
+            gmp::mpz_set(&mut p2.a as *mut mpz_t, &xy.a as *const mpz_t);
+            gmp::mpz_set(&mut p2.b as *mut mpz_t, &xy.b as *const mpz_t);
+        }
+        eprintln!("e done");
+        WrappedMpzBi { a: xy.a, b: xy.b }
+    }
+}
+
+pub async fn compute_pi(digits: u64, prec: u64) -> WrappedMpf {
+    let n = terms_for_digits(&Chudnovsky, digits);
+    unsafe {
+        let e_handle = tokio::spawn(async move { calc_sqrt_pell(digits).await });
+        let pqt: PQT = sum_series(&Chudnovsky, n).await;
+        eprintln!("pqt done");
+        let wrap_q = WrappedMpz { a: pqt.q };
+        let wrap_t = WrappedMpz { a: pqt.t };
+        let q_handle = tokio::spawn(async move {
+            let q = mpf_cast(wrap_q, prec).await.a;
+            let wrap_q_mpf = WrappedMpf { a: q };
+            let mut q_clone = allocate_mpf(0, prec);
+            gmp::mpf_set(&mut q_clone, &q as *const mpf_t);
+            let wrap_q_mpf_clone = WrappedMpf { a: q_clone };
+            let d_handle = tokio::spawn(async move {
+                let mut d = allocate_mpf(D, prec);
+                let q = wrap_q_mpf;
+                let wrap = WrappedMpfTri { a: d, b: d, c: q.a };
+                d = mpf_mul(wrap).await.a;
+                WrappedMpf { a: d }
+            });
+            let a_handle = tokio::spawn(async move {
+                let mut a = allocate_mpf(A, prec);
+                let q = wrap_q_mpf_clone;
+                let wrap = WrappedMpfTri { a, b: a, c: q.a };
+                a = mpf_mul(wrap).await.a;
+                eprintln!("a done");
+                WrappedMpf { a }
+            });
+            (d_handle, a_handle)
+        });
+        let t_handle = tokio::spawn(async move { mpf_cast(wrap_t, prec).await });
+        let (d_handle, a_handle) = q_handle.await.unwrap();
+        let bottom_handle = tokio::spawn(async {
+            let a_grip = a_handle.await.unwrap();
+            let t_grip = t_handle.await.unwrap();
+            let mut a = a_grip.a;
+            let t = t_grip;
+            gmp::mpf_add(
+                &mut a as *mut mpf_t,
+                &a as *const mpf_t,
+                &t.a as *const mpf_t,
+            );
+            WrappedMpf { a }
+        });
+        let e_comp = e_handle.await.unwrap();
+        let e_f = e_comp;
+        let e_x = WrappedMpz { a: e_f.a };
+        let e_y = WrappedMpz { a: e_f.b };
+        let top_handle = tokio::spawn(async move {
+            let e_x_mpf = mpf_cast(e_x, prec).await;
+            let d_grip = d_handle.await.unwrap();
+            let mut d = d_grip.a;
+            gmp::mpf_mul(
+                &mut d as *mut mpf_t,
+                &e_x_mpf.a as *const mpf_t,
+                &d as *const mpf_t,
+            );
+            WrappedMpf { a: d }
+        });
+        let mut pi = allocate_mpf(0, prec);
+        let bottom_mul_handle = tokio::spawn(async move {
+            let e_y_mpf = mpf_cast(e_y, prec).await;
+            let mut bottom = bottom_handle.await.unwrap().a;
+            gmp::mpf_mul(
+                &mut bottom as *mut mpf_t,
+                &bottom as *const mpf_t,
+                &e_y_mpf.a as *const mpf_t,
+            );
+            WrappedMpf { a: bottom }
+        });
+        let top = top_handle.await.unwrap().a;
+        let bottom = bottom_mul_handle.await.unwrap().a;
+        gmp::mpf_div(
+            &mut pi as *mut mpf_t,
+            &top as *const mpf_t,
+            &bottom as *const mpf_t,
+        );
+        WrappedMpf { a: pi }
+    }
+}
@@ -0,0 +1,249 @@
+use gmp_mpfr_sys::gmp;
+
+use crate::series::{Poly, Series, eval_series, terms_for_digits};
+use crate::{WrappedMpf, allocate_mpf};
+
+// e = sum_{k >= 0} 1 / k!
+pub struct E;
+
+impl Series for E {
+    fn p(&self) -> Vec<Poly> {
+        vec![]
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        vec![Poly::new(&[0, 1])]
+    }
+
+    fn a(&self) -> Poly {
+        Poly::new(&[1])
+    }
+}
+
+pub async fn compute_e(digits: u64, prec: u64) -> WrappedMpf {
+    eval_series(&E, terms_for_digits(&E, digits), prec).await
+}
+
+// atanh(1/x) = sum_{k >= 0} 1 / ((2k + 1) x^(2k + 1)), summed as x atanh(1/x). the term
+// ratio (2k - 1) / ((2k + 1) x^2) telescopes, and most of what that adds to P and Q is
+// taken back out by the common factor cancellation
+pub struct Atanh {
+    x: i64,
+}
+
+impl Series for Atanh {
+    fn p(&self) -> Vec<Poly> {
+        vec![Poly::new(&[-1, 2])]
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        vec![Poly::new(&[1, 2]), Poly::new(&[self.x * self.x])]
+    }
+
+    fn a(&self) -> Poly {
+        Poly::new(&[1])
+    }
+}
+
+// ln 2, ln 3 and ln 5 as combinations of atanh(1/x) for these x
+const LN_ATANH_X: [i64; 4] = [251, 449, 4801, 8749];
+const LN_COEFFS: [(u64, [i64; 4]); 3] = [
+    (2, [144, 54, -38, 62]),
+    (3, [228, 86, -60, 98]),
+    (5, [334, 126, -88, 144]),
+];
+
+// sum of c * s(x) / x over (x, c), where s(x) is x times the series in 1/x. the
+// series are run concurrently
+async fn combine_inverse<S: Series + 'static>(
+    terms: &[(i64, i64)],
+    make: fn(i64) -> S,
+    digits: u64,
+    prec: u64,
+) -> WrappedMpf {
+    // the coefficients scale up any error in the individual series
+    let digits = digits
+        + terms
+            .iter()
+            .map(|(_, c)| c.unsigned_abs())
+            .max()
+            .unwrap()
+            .ilog10() as u64;
+    let handles: Vec<_> = terms
+        .iter()
+        .map(|&(x, _)| {
+            tokio::spawn(async move {
+                let series = make(x);
+                let n = terms_for_digits(&series, digits);
+                eval_series(&series, n, prec).await
+            })
+        })
+        .collect();
+    let mut sums = Vec::with_capacity(handles.len());
+    for handle in handles {
+        sums.push(handle.await.unwrap());
+    }
+    unsafe {
+        let mut total = allocate_mpf(0, prec);
+        for (sum, &(x, c)) in sums.into_iter().zip(terms) {
+            let mut s = sum.a;
+            gmp::mpf_div_ui(&mut s, &s, x as u64);
+            gmp::mpf_mul_ui(&mut s, &s, c.unsigned_abs());
+            if c < 0 {
+                gmp::mpf_sub(&mut total, &total, &s);
+            } else {
+                gmp::mpf_add(&mut total, &total, &s);
+            }
+            gmp::mpf_clear(&mut s);
+        }
+        WrappedMpf { a: total }
+    }
+}
+
+// ln(n) for any n = 2^a 3^b 5^c, from the four atanh series run concurrently
+pub async fn compute_ln(n: u64, digits: u64, prec: u64) -> WrappedMpf {
+    let mut coeffs = [0i64; 4];
+    let mut rest = n;
+    for (prime, c) in LN_COEFFS.iter() {
+        while rest > 1 && rest.is_multiple_of(*prime) {
+            rest /= prime;
+            for (acc, c) in coeffs.iter_mut().zip(c.iter()) {
+                *acc += c;
+            }
+        }
+    }
+    assert!(n > 1 && rest == 1, "ln only supports n = 2^a 3^b 5^c > 1");
+    let terms: Vec<(i64, i64)> = LN_ATANH_X.into_iter().zip(coeffs).collect();
+    combine_inverse(&terms, |x| Atanh { x }, digits, prec).await
+}
+
+// atan(1/x), the same series as atanh with alternating signs
+pub struct Atan {
+    x: i64,
+}
+
+impl Series for Atan {
+    fn p(&self) -> Vec<Poly> {
+        // -(2k - 1)
+        vec![Poly::new(&[1, -2])]
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        vec![Poly::new(&[1, 2]), Poly::new(&[self.x * self.x])]
+    }
+
+    fn a(&self) -> Poly {
+        Poly::new(&[1])
+    }
+}
+
+// pi = sum c atan(1/x) over (x, c)
+pub const MACHIN: [(i64, i64); 2] = [(5, 16), (239, -4)];
+pub const TAKANO: [(i64, i64); 4] = [(49, 48), (57, 128), (239, -20), (110443, 48)];
+pub const STORMER: [(i64, i64); 4] = [(57, 176), (239, 28), (682, -48), (12943, 96)];
+
+pub async fn compute_pi_atan(formula: &[(i64, i64)], digits: u64, prec: u64) -> WrappedMpf {
+    combine_inverse(formula, |x| Atan { x }, digits, prec).await
+}
+
+// zeta(3) = 1/64 sum_{k >= 0} (-1)^k (k!)^10 (205k^2 + 250k + 77) / ((2k + 1)!)^5,
+// from Amdeberhan and Zeilberger
+pub struct Zeta3;
+
+impl Series for Zeta3 {
+    fn p(&self) -> Vec<Poly> {
+        // -k^5
+        let mut p = vec![Poly::new(&[-1])];
+        p.extend((0..5).map(|_| Poly::new(&[0, 1])));
+        p
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        // 32 (2k + 1)^5
+        let mut q = vec![Poly::new(&[32])];
+        q.extend((0..5).map(|_| Poly::new(&[1, 2])));
+        q
+    }
+
+    fn a(&self) -> Poly {
+        Poly::new(&[77, 250, 205])
+    }
+}
+
+pub async fn compute_zeta3(digits: u64, prec: u64) -> WrappedMpf {
+    let mut zeta3 = eval_series(&Zeta3, terms_for_digits(&Zeta3, digits), prec).await;
+    unsafe {
+        gmp::mpf_div_ui(&mut zeta3.a, &zeta3.a, 64);
+    }
+    zeta3
+}
+
+// Lupas' series G = 1/64 sum_{n >= 1} (-1)^(n - 1) 256^n (40n^2 - 24n + 3) ((2n)!)^3 (n!)^2
+// / (n^3 (2n - 1) ((4n)!)^2), shifted to start at k = n - 1 = 0. the first term is 32/9,
+// which leaves G = S / 18
+pub struct Catalan;
+
+impl Series for Catalan {
+    fn p(&self) -> Vec<Poly> {
+        // -32 k^3 (2k - 1)
+        vec![
+            Poly::new(&[-32]),
+            Poly::new(&[0, 1]),
+            Poly::new(&[0, 1]),
+            Poly::new(&[0, 1]),
+            Poly::new(&[-1, 2]),
+        ]
+    }
+
+    fn q(&self) -> Vec<Poly> {
+        // (4k + 3)^2 (4k + 1)^2
+        vec![
+            Poly::new(&[3, 4]),
+            Poly::new(&[3, 4]),
+            Poly::new(&[1, 4]),
+            Poly::new(&[1, 4]),
+        ]
+    }
+
+    fn a(&self) -> Poly {
+        // 40n^2 - 24n + 3 at n = k + 1
+        Poly::new(&[19, 56, 40])
+    }
+}
+
+pub async fn compute_catalan(digits: u64, prec: u64) -> WrappedMpf {
+    let mut catalan = eval_series(&Catalan, terms_for_digits(&Catalan, digits), prec).await;
+    unsafe {
+        gmp::mpf_div_ui(&mut catalan.a, &catalan.a, 18);
+    }
+    catalan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chudnovsky, make_cstr_mpf_exp};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn atan_formulas_agree_with_chudnovsky() {
+        for digits in [50, 300, 700] {
+            let prec = (digits as f64 * 10f64.log2()) as u64 + 64;
+            let mut chud = chudnovsky::compute_pi(digits, prec).await.a;
+            let expected = make_cstr_mpf_exp(chud, digits as usize, 10);
+            for formula in [&MACHIN[..], &TAKANO, &STORMER] {
+                let mut pi = compute_pi_atan(formula, digits, prec).await.a;
+                assert_eq!(
+                    make_cstr_mpf_exp(pi, digits as usize, 10),
+                    expected,
+                    "{digits} digits"
+                );
+                unsafe {
+                    gmp::mpf_clear(&mut pi);
+                }
+            }
+            unsafe {
+                gmp::mpf_clear(&mut chud);
+            }
+        }
+    }
+}
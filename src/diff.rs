@@ -0,0 +1,196 @@
+use crate::source::{CHUNK, Source};
+
+// digits shown either side of the first mismatch
+const CONTEXT: usize = 20;
+
+// the last CONTEXT digits of prev followed by cur
+fn tail(prev: &[u8], cur: &[u8]) -> Vec<u8> {
+    let mut t: Vec<u8> = prev.iter().chain(cur.iter()).copied().collect();
+    t.drain(..t.len().saturating_sub(CONTEXT));
+    t
+}
+
+// the first mismatch, at position at, with the digits either side of it in a and b.
+// after starts with the mismatching digit and is filled in from later chunks when
+// the mismatch is near the end of one
+struct Mismatch {
+    at: u64,
+    before: [Vec<u8>; 2],
+    after: [Vec<u8>; 2],
+}
+
+impl Mismatch {
+    // the context in one file, the mismatching digit in brackets
+    fn context(&self, side: usize) -> String {
+        let after = &self.after[side];
+        format!(
+            "{}[{}]{}",
+            String::from_utf8_lossy(&self.before[side]),
+            after[0] as char,
+            String::from_utf8_lossy(&after[1..])
+        )
+    }
+}
+
+struct Comparison {
+    // positions start..end were compared
+    start: u64,
+    end: u64,
+    mismatches: u64,
+    first: Option<Mismatch>,
+    // which file goes on past end when the other one stops, if either does
+    a_longer: Option<bool>,
+}
+
+// compares the positions both sources hold, chunk digits at a time
+fn compare(a: &mut Source, b: &mut Source, chunk: usize) -> Comparison {
+    let start = a.offset.max(b.offset);
+    a.skip(start - a.offset);
+    b.skip(start - b.offset);
+
+    let (mut buf_a, mut buf_b) = (Vec::new(), Vec::new());
+    // carried over between chunks, for the context before a mismatch
+    let (mut prev_a, mut prev_b) = (Vec::new(), Vec::new());
+    let mut pos = start;
+    let mut first: Option<Mismatch> = None;
+    let mut mismatches = 0u64;
+    let mut a_longer = None;
+    loop {
+        a.read_chunk(&mut buf_a, chunk);
+        b.read_chunk(&mut buf_b, chunk);
+        let n = buf_a.len().min(buf_b.len());
+        if let Some(f) = first.as_mut() {
+            let more = (CONTEXT - f.after[0].len()).min(n);
+            f.after[0].extend_from_slice(&buf_a[..more]);
+            f.after[1].extend_from_slice(&buf_b[..more]);
+        }
+        for i in 0..n {
+            if buf_a[i] == buf_b[i] {
+                continue;
+            }
+            mismatches += 1;
+            if first.is_none() {
+                let end = (i + CONTEXT).min(n);
+                first = Some(Mismatch {
+                    at: pos + i as u64,
+                    before: [tail(&prev_a, &buf_a[..i]), tail(&prev_b, &buf_b[..i])],
+                    after: [buf_a[i..end].to_vec(), buf_b[i..end].to_vec()],
+                });
+            }
+        }
+        pos += n as u64;
+        if buf_a.len() != buf_b.len() {
+            a_longer = Some(buf_a.len() > buf_b.len());
+            break;
+        }
+        if n == 0 {
+            break;
+        }
+        prev_a = tail(&prev_a, &buf_a);
+        prev_b = tail(&prev_b, &buf_b);
+    }
+    Comparison {
+        start,
+        end: pos,
+        mismatches,
+        first,
+        a_longer,
+    }
+}
+
+// pi-thing diff <a> <b>: compares two digit files, text or packed, a chunk at a time.
+// packed files can start at an offset, so only the positions both hold are compared
+pub fn run(args: &[String]) {
+    let (path_a, path_b) = (&args[0], &args[1]);
+    let (mut a, mut b) = (Source::open(path_a), Source::open(path_b));
+    let c = compare(&mut a, &mut b, CHUNK);
+    let (start, pos) = (c.start, c.end);
+    if let Some(a_longer) = c.a_longer {
+        let (longer, shorter) = if a_longer {
+            (path_a, path_b)
+        } else {
+            (path_b, path_a)
+        };
+        println!("{shorter} ends at position {pos}, {longer} goes on");
+    }
+
+    let compared = pos - start;
+    println!("compared positions {start}..{pos}");
+    println!(
+        "{} matching, {} different",
+        compared - c.mismatches,
+        c.mismatches
+    );
+    match c.first {
+        Some(f) => {
+            println!(
+                "first mismatch at position {} ({} matching before it)",
+                f.at,
+                f.at - start
+            );
+            println!("  {path_a}: {}", f.context(0));
+            println!("  {path_b}: {}", f.context(1));
+            std::process::exit(1);
+        }
+        None => println!("no mismatches"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packed;
+    use crate::temp_path;
+
+    #[test]
+    fn first_mismatch_and_its_context() {
+        let digits: Vec<u8> = (0..200).map(|i| b'0' + (i * 3 % 10) as u8).collect();
+        let (path_a, path_b) = (temp_path("diff-a.txt"), temp_path("diff-b.pdig"));
+        std::fs::write(&path_a, &digits).unwrap();
+        // b is packed and starts at position 10, so only 10.. is compared
+        let header = packed::Header {
+            constant: "test".to_string(),
+            radix: 10,
+            digits: 190,
+            offset: 10,
+            exponent: 1,
+        };
+        let mut packed_other = digits.clone();
+        packed_other[50] = b'7';
+        packed_other[120] = b'8';
+        packed::write(&path_b, &header, &packed_other[10..]);
+        // chunks that end inside the context, and one chunk for everything
+        for chunk in [7, 64, 1000] {
+            let c = compare(
+                &mut Source::open(&path_a),
+                &mut Source::open(&path_b),
+                chunk,
+            );
+            assert_eq!(
+                (c.start, c.end, c.mismatches),
+                (10, 200, 2),
+                "chunk {chunk}"
+            );
+            assert_eq!(c.a_longer, None);
+            let f = c.first.unwrap();
+            assert_eq!(f.at, 50);
+            let around = |d: &[u8], mid: u8| {
+                format!(
+                    "{}[{}]{}",
+                    String::from_utf8_lossy(&d[30..50]),
+                    mid as char,
+                    String::from_utf8_lossy(&d[51..70])
+                )
+            };
+            assert_eq!(f.context(0), around(&digits, digits[50]));
+            assert_eq!(f.context(1), around(&packed_other, b'7'));
+        }
+
+        // a shorter text file stops the comparison where it ends
+        std::fs::write(&path_a, &digits[..100]).unwrap();
+        let c = compare(&mut Source::open(&path_a), &mut Source::open(&path_b), 64);
+        assert_eq!((c.end, c.mismatches, c.a_longer), (100, 1, Some(false)));
+        std::fs::remove_file(&path_a).unwrap();
+        std::fs::remove_file(&path_b).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+
+use crate::bbp::frac;
+use crate::fac::PrimeWindows;
+use crate::flag_value;
+use crate::modarith::{mul_mod, pow_mod};
+
+// primes per spawned task
+const CHUNK: usize = 64;
+// chunks spawned and not yet summed
+const IN_FLIGHT: usize = 64;
+const MAX_COUNT: u64 = 20;
+
+// a^-1 mod m, for a coprime to m
+fn inv_mod(a: u64, m: u64) -> u64 {
+    let (mut r0, mut r1) = (m as i128, a as i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    t0.rem_euclid(m as i128) as u64
+}
+
+// n = p^v x with x coprime to p
+fn strip(mut n: u64, p: u64) -> (u64, i64) {
+    let mut v = 0;
+    while n.is_multiple_of(p) {
+        n /= p;
+        v += 1;
+    }
+    (n, v)
+}
+
+// the part of frac(10^n S) that has a power of p in its denominator, with S the first
+// terms of sum_{k >= 1} k B(k), B(k) = prod_{i <= k} i / (2i - 1). every denominator
+// divides p^e with e = floor(log_p(2 terms)), so the whole sum can be kept mod p^e:
+// units as a / b, the power of p in v
+fn prime_part(p: u64, n: u64, terms: u64) -> u128 {
+    let e = (2 * terms).ilog(p);
+    let m = p.pow(e);
+    // 10^n = p^w unit
+    let (unit, w) = if p == 5 {
+        (pow_mod(2, n, m), n as i64)
+    } else {
+        (pow_mod(10, n, m), 0)
+    };
+    let (mut a, mut b, mut v, mut sum) = (1u64, 1u64, 0i64, 0u64);
+    for k in 1..=terms {
+        let (x, vx) = strip(k, p);
+        let (y, vy) = strip(2 * k - 1, p);
+        a = mul_mod(a, x, m);
+        b = mul_mod(b, y, m);
+        sum = mul_mod(sum, y, m);
+        v += vx - vy;
+        // the k B(k) term, as long as it isn't a p-adic integer
+        let shift = v + vx + w;
+        if shift < 0 {
+            let term = mul_mod(mul_mod(x, a, m), p.pow((e as i64 + shift) as u32), m);
+            sum = (sum + term) % m;
+        }
+    }
+    let c = mul_mod(mul_mod(sum, inv_mod(b, m), m), unit, m);
+    frac(c, m)
+}
+
+// pi + 3 = sum_{k >= 1} k 2^k / C(2k, k), whose terms only have odd primes below 2k in
+// their denominators. so frac(10^n pi) is the sum over those primes of a fraction that
+// only needs word sized modular arithmetic: O(n^2) time, O(1) memory per prime. the
+// primes come a window at a time and at most IN_FLIGHT chunks of them are pending, but
+// sieving them needs the primes up to sqrt(2 terms), so memory is O(sqrt(n)) words
+// rather than O(log n)
+async fn pi_frac(n: u64, count: u64) -> u128 {
+    // k 2^k / C(2k, k) ~ k^1.5 2^-k, and the dropped terms times 10^n have to stay
+    // below the digits wanted
+    let bits = (n + count + 2) as f64 * 10f64.log2();
+    let mut terms = bits as u64;
+    while (terms as f64) < bits + 1.5 * (terms as f64).log2() + 4.0 {
+        terms += 1;
+    }
+    eprintln!("{terms} terms");
+    let mut pending = VecDeque::new();
+    let (mut total, mut primes) = (0u128, 0u64);
+    for window in PrimeWindows::new(2 * terms) {
+        primes += window.len() as u64;
+        for chunk in window.chunks(CHUNK) {
+            let chunk = chunk.to_vec();
+            pending.push_back(tokio::spawn(async move {
+                chunk
+                    .iter()
+                    .fold(0u128, |acc, &p| acc.wrapping_add(prime_part(p, n, terms)))
+            }));
+            if pending.len() >= IN_FLIGHT {
+                let part: u128 = pending.pop_front().unwrap().await.unwrap();
+                total = total.wrapping_add(part);
+            }
+        }
+    }
+    for handle in pending {
+        total = total.wrapping_add(handle.await.unwrap());
+    }
+    eprintln!("{primes} primes");
+    total
+}
+
+// pi-thing digit-at --position <n> [--count <k>]: k decimal digits of pi starting at the
+// n-th digit after the point
+pub async fn run(args: &[String]) {
+    let position = flag_value(args, "--position")
+        .expect("digit-at needs --position")
+        .parse::<u64>()
+        .unwrap();
+    assert!(position >= 1, "positions start at 1");
+    let count = flag_value(args, "--count").map_or(10, |c| c.parse::<u64>().unwrap());
+    assert!(count <= MAX_COUNT, "at most {MAX_COUNT} digits at a time");
+    eprintln!("Computing decimal digits of pi from position {position}");
+    println!("{}", digits_at(position, count).await);
+}
+
+async fn digits_at(position: u64, count: u64) -> String {
+    let mut f = pi_frac(position - 1, count).await;
+    let mut digits = String::new();
+    for _ in 0..count {
+        // f * 10, the integer part is the next digit
+        let lo = (f as u64 as u128) * 10;
+        let hi = (f >> 64) * 10 + (lo >> 64);
+        digits.push(char::from(b'0' + (hi >> 64) as u8));
+        f = ((hi & u64::MAX as u128) << 64) | (lo & u64::MAX as u128);
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn known_decimal_digits() {
+        // pi = 3.14159265358979323846...
+        assert_eq!(digits_at(1, 10).await, "1415926535");
+        assert_eq!(digits_at(11, 10).await, "8979323846");
+        // the six 9s (the Feynman point) start at position 762
+        assert_eq!(digits_at(760, 10).await, "3499999983");
+        assert_eq!(digits_at(1000, 5).await, "93809");
+    }
+}
@@ -0,0 +1,337 @@
+use gmp_mpfr_sys::{gmp, gmp::mpz_t};
+
+use crate::allocate_mpz;
+use crate::modarith::{mul_mod, pow_mod};
+
+// values above this are kept as (possibly composite) bases instead of being sieved.
+// cancelling still works with composite bases, it just finds fewer common factors
+const SIEVE_LIMIT: u64 = 1 << 26;
+// numbers sieved at a time by PrimeWindows
+const WINDOW: u64 = 1 << 20;
+
+// smallest prime factor of every odd number below bound, indexed by n / 2
+pub struct Sieve {
+    bound: u64,
+    spf: Vec<u32>,
+}
+
+impl Sieve {
+    pub fn new(bound: u64) -> Sieve {
+        let bound = bound.clamp(3, SIEVE_LIMIT);
+        let mut spf = vec![0u32; bound.div_ceil(2) as usize];
+        let mut i = 3;
+        while i * i < bound {
+            if spf[(i / 2) as usize] == 0 {
+                let mut j = i * i;
+                while j < bound {
+                    if spf[(j / 2) as usize] == 0 {
+                        spf[(j / 2) as usize] = i as u32;
+                    }
+                    j += 2 * i;
+                }
+            }
+            i += 2;
+        }
+        Sieve { bound, spf }
+    }
+
+    // n must be odd and below the bound
+    pub fn is_prime(&self, n: u64) -> bool {
+        n > 1 && self.spf[(n / 2) as usize] == 0
+    }
+
+    fn smallest_factor(&self, n: u64) -> u64 {
+        match self.spf[(n / 2) as usize] {
+            0 => n,
+            p => p as u64,
+        }
+    }
+}
+
+// the odd primes below bound, one window of WINDOW numbers at a time, so memory is
+// the primes up to sqrt(bound) plus a window however large bound gets
+pub struct PrimeWindows {
+    base: Vec<u64>,
+    lo: u64,
+    bound: u64,
+}
+
+impl PrimeWindows {
+    pub fn new(bound: u64) -> PrimeWindows {
+        let root = bound.isqrt() + 1;
+        let small = Sieve::new(root + 1);
+        assert!(small.bound > root, "bound too large to sieve");
+        PrimeWindows {
+            base: (3..=root)
+                .step_by(2)
+                .filter(|&p| small.is_prime(p))
+                .collect(),
+            lo: 3,
+            bound,
+        }
+    }
+}
+
+impl Iterator for PrimeWindows {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Vec<u64>> {
+        if self.lo >= self.bound {
+            return None;
+        }
+        // lo is always odd, composite[i] is for lo + 2i
+        let (lo, hi) = (self.lo, (self.lo + WINDOW).min(self.bound));
+        let mut composite = vec![false; (hi - lo).div_ceil(2) as usize];
+        for &p in self.base.iter().take_while(|&&p| p * p < hi) {
+            // the first odd multiple of p in the window, and never p itself
+            let mut j = (p * p).max(lo.div_ceil(p) * p);
+            if j.is_multiple_of(2) {
+                j += p;
+            }
+            while j < hi {
+                composite[((j - lo) / 2) as usize] = true;
+                j += 2 * p;
+            }
+        }
+        self.lo = hi + (hi % 2 == 0) as u64;
+        Some(
+            composite
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| !**c)
+                .map(|(i, _)| lo + 2 * i as u64)
+                .collect(),
+        )
+    }
+}
+
+// a number as a product of base^exp, sorted by base. bases are primes unless they
+// were too large to sieve, in which case they are the leftover cofactor
+#[derive(Default, Clone)]
+pub struct Fac {
+    f: Vec<(u64, u32)>,
+}
+
+impl Fac {
+    pub fn from_value(mut n: u64, sieve: &Sieve) -> Fac {
+        let mut f: Vec<(u64, u32)> = Vec::new();
+        let twos = n.trailing_zeros();
+        if twos > 0 {
+            f.push((2, twos));
+            n >>= twos;
+        }
+        while n > 1 {
+            let p = if n < sieve.bound {
+                sieve.smallest_factor(n)
+            } else {
+                n
+            };
+            n /= p;
+            match f.last_mut() {
+                Some((b, e)) if *b == p => *e += 1,
+                _ => f.push((p, 1)),
+            }
+        }
+        // the spf walk yields factors in ascending order, except a leftover cofactor
+        // which is always last and larger than anything before it
+        Fac { f }
+    }
+
+    // product of many small values, factored in one go
+    pub fn from_values(values: &[u64], sieve: &Sieve) -> Fac {
+        let mut raw: Vec<(u64, u32)> = Vec::with_capacity(values.len() * 4);
+        for &v in values {
+            raw.extend(Fac::from_value(v, sieve).f);
+        }
+        raw.sort_unstable_by_key(|&(b, _)| b);
+        let mut f: Vec<(u64, u32)> = Vec::with_capacity(raw.len());
+        for (b, e) in raw {
+            match f.last_mut() {
+                Some((last, acc)) if *last == b => *acc += e,
+                _ => f.push((b, e)),
+            }
+        }
+        Fac { f }
+    }
+
+    // for constants, which can be well past the sieve. trial division is fine as
+    // these are only factored once per series
+    pub fn from_const(mut n: u64) -> Fac {
+        let mut f: Vec<(u64, u32)> = Vec::new();
+        let mut d = 2;
+        while d * d <= n && d < 1 << 16 {
+            let mut e = 0;
+            while n.is_multiple_of(d) {
+                n /= d;
+                e += 1;
+            }
+            if e > 0 {
+                f.push((d, e));
+            }
+            d += 1;
+        }
+        if n > 1 {
+            f.push((n, 1));
+        }
+        Fac { f }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.f.is_empty()
+    }
+
+    pub fn pow(mut self, k: u32) -> Fac {
+        for (_, e) in self.f.iter_mut() {
+            *e *= k;
+        }
+        self
+    }
+
+    pub fn mul(&self, other: &Fac) -> Fac {
+        let (a, b) = (&self.f, &other.f);
+        let mut f = Vec::with_capacity(a.len() + b.len());
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i].0 == b[j].0 {
+                f.push((a[i].0, a[i].1 + b[j].1));
+                i += 1;
+                j += 1;
+            } else if a[i].0 < b[j].0 {
+                f.push(a[i]);
+                i += 1;
+            } else {
+                f.push(b[j]);
+                j += 1;
+            }
+        }
+        f.extend_from_slice(&a[i..]);
+        f.extend_from_slice(&b[j..]);
+        Fac { f }
+    }
+
+    pub fn gcd(&self, other: &Fac) -> Fac {
+        let (a, b) = (&self.f, &other.f);
+        let mut f = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i].0 == b[j].0 {
+                f.push((a[i].0, a[i].1.min(b[j].1)));
+                i += 1;
+                j += 1;
+            } else if a[i].0 < b[j].0 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Fac { f }
+    }
+
+    // other must divide self
+    pub fn div(&mut self, other: &Fac) {
+        let mut j = 0;
+        for (b, e) in self.f.iter_mut() {
+            if j < other.f.len() && other.f[j].0 == *b {
+                *e -= other.f[j].1;
+                j += 1;
+            }
+        }
+        self.f.retain(|&(_, e)| e > 0);
+    }
+
+    // the value mod m
+    pub fn rem(&self, m: u64) -> u64 {
+        self.f.iter().fold(1 % m, |acc, &(b, e)| {
+            mul_mod(acc, pow_mod(b, e as u64, m), m)
+        })
+    }
+
+    pub fn to_mpz(&self) -> mpz_t {
+        let mut z = allocate_mpz(1);
+        let mut acc: u64 = 1;
+        unsafe {
+            for &(b, e) in self.f.iter() {
+                for _ in 0..e {
+                    match acc.checked_mul(b) {
+                        Some(v) => acc = v,
+                        None => {
+                            gmp::mpz_mul_ui(&mut z as *mut mpz_t, &z as *const mpz_t, acc);
+                            acc = b;
+                        }
+                    }
+                }
+            }
+            gmp::mpz_mul_ui(&mut z as *mut mpz_t, &z as *const mpz_t, acc);
+        }
+        z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_cstr_mpz;
+
+    fn value(f: &Fac) -> u128 {
+        let mut z = f.to_mpz();
+        let v = make_cstr_mpz(z).parse().unwrap();
+        unsafe { gmp::mpz_clear(&mut z) };
+        v
+    }
+
+    fn gcd(a: u128, b: u128) -> u128 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+
+    #[test]
+    fn arithmetic_matches_the_values() {
+        let sieve = Sieve::new(1000);
+        // 997 * 991 and 1009 are past the sieve and stay whole
+        let values = [1, 2, 12, 360, 997 * 991, 1009, 720720, 999, 3 << 40];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                let (fa, fb) = (Fac::from_value(a, &sieve), Fac::from_value(b, &sieve));
+                let (a, b) = (a as u128, b as u128);
+                assert_eq!(value(&fa.mul(&fb)), a * b);
+                let mut q = fa.mul(&fb);
+                q.div(&fb);
+                assert_eq!(value(&q), a);
+                assert_eq!(value(&fa.clone().pow(2)), a * a);
+                assert_eq!(fa.rem(1_000_003), (a % 1_000_003) as u64);
+                // whole cofactors share nothing but themselves
+                if a < 1000 && b < 1000 {
+                    assert_eq!(value(&fa.gcd(&fb)), gcd(a, b));
+                }
+            }
+        }
+        assert!(Fac::from_value(1, &sieve).is_empty());
+    }
+
+    #[test]
+    fn from_values_and_const_agree_with_from_value() {
+        let sieve = Sieve::new(1 << 12);
+        let f = Fac::from_values(&[6, 10, 15, 4095, 1], &sieve);
+        assert_eq!(value(&f), 6 * 10 * 15 * 4095);
+        assert_eq!(
+            value(&Fac::from_const(10939058860032000)),
+            10939058860032000
+        );
+        assert_eq!(
+            value(&Fac::from_const(640320).gcd(&Fac::from_value(4095, &sieve))),
+            gcd(640320, 4095)
+        );
+    }
+
+    #[test]
+    fn prime_windows_match_the_sieve() {
+        let bound = 2 * WINDOW + 12345;
+        let sieve = Sieve::new(bound);
+        let expected: Vec<u64> = (3..bound)
+            .step_by(2)
+            .filter(|&n| sieve.is_prime(n))
+            .collect();
+        let windows: Vec<Vec<u64>> = PrimeWindows::new(bound).collect();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows.concat(), expected);
+    }
+}
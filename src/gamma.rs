@@ -0,0 +1,260 @@
+use gmp_mpfr_sys::{gmp, gmp::mpz_t};
+use std::time::Instant;
+
+use crate::constants::compute_ln;
+use crate::manifest;
+use crate::series::{LEAF_TERMS, THRESH, mpz_mul_u128};
+use crate::{WrappedMpf, WrappedMpz, WrappedMpzTri, allocate_mpf, allocate_mpz, mpf_cast};
+
+// Brent-McMillan: with a(k) = (n^k / k!)^2 and H(k) the k-th harmonic number,
+// gamma = sum a(k) H(k) / sum a(k) - ln(n), to within pi e^(-4n).
+//
+// over a range of terms (n1, n2] with ratio a(k) / a(k - 1) = n^2 / k^2:
+//   Q = prod k^2, D = prod k, C / D = sum 1 / k,
+//   T / Q = sum of a(k) / a(n1),
+//   U / (Q D) = sum of a(k) / a(n1) * (H(k) - H(n1)).
+// P = n^(2 (n2 - n1)) is a power, so it is only computed where a merge needs it.
+// a(k) H(k) isn't a hypergeometric term (H(k) / H(k - 1) isn't rational in k), so
+// the U sum needs the extra C, D pair and can't go through series::sum_series
+struct Sums {
+    q: mpz_t,
+    d: mpz_t,
+    c: mpz_t,
+    t: mpz_t,
+    u: mpz_t,
+}
+
+unsafe impl Send for Sums {}
+
+impl Sums {
+    fn new() -> Sums {
+        Sums {
+            q: allocate_mpz(0),
+            d: allocate_mpz(0),
+            c: allocate_mpz(0),
+            t: allocate_mpz(0),
+            u: allocate_mpz(0),
+        }
+    }
+
+    unsafe fn clear(&mut self) {
+        unsafe {
+            gmp::mpz_clear(&mut self.q);
+            gmp::mpz_clear(&mut self.d);
+            gmp::mpz_clear(&mut self.c);
+            gmp::mpz_clear(&mut self.t);
+            gmp::mpz_clear(&mut self.u);
+        }
+    }
+}
+
+// smallest 2^a 3^b 5^c >= target, so ln(n) is one of the atanh combinations
+fn smooth_at_least(target: u64) -> u64 {
+    let mut best = u64::MAX;
+    let mut p2 = 1u64;
+    while p2 < best {
+        let mut p3 = p2;
+        while p3 < best {
+            let mut p5 = p3;
+            while p5 < target {
+                p5 *= 5;
+            }
+            best = best.min(p5);
+            p3 *= 3;
+        }
+        p2 *= 2;
+    }
+    best
+}
+
+// smallest number of terms so the first dropped term, relative to the sum (about
+// e^(2n)), is below 10^-digits. series::terms_for_digits doesn't fit here, it assumes
+// terms shrink from the start, and these grow until k = n before they decrease
+fn terms_for_digits(n: u64, digits: u64) -> u64 {
+    let nf = n as f64;
+    let log_term = |k: u64| {
+        let k = k as f64;
+        // 2 ln(n^k / k!) with Stirling for ln(k!)
+        2.0 * (k * nf.ln() - (k * k.ln() - k + 0.5 * (2.0 * std::f64::consts::PI * k).ln()))
+    };
+    let target = 2.0 * nf - (digits + 2) as f64 * 10f64.ln();
+    let (mut lo, mut hi) = (n.max(1), 8 * n.max(1));
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if log_term(mid) < target {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    lo + 2
+}
+
+// terms n1 + 1 ..= n2 appended one at a time, with P = n^(2 (k - n1)) for the term
+// being added: c = c k + d, t = t k^2 + P, u = u k^3 + P c, d *= k and q *= k^2. q
+// is only ever multiplied, so it is built up in a u128 over several terms first
+fn leaf_sums(n: u64, n1: u64, n2: u64) -> Sums {
+    let mut s = Sums::new();
+    unsafe {
+        let (mut p, mut tmp) = (allocate_mpz(1), allocate_mpz(0));
+        gmp::mpz_set_ui(&mut s.q, 1);
+        gmp::mpz_set_ui(&mut s.d, 1);
+        let mut q_acc: u128 = 1;
+        for k in n1 + 1..=n2 {
+            gmp::mpz_mul_ui(&mut p, &p, n * n);
+            gmp::mpz_mul_ui(&mut s.c, &s.c, k);
+            gmp::mpz_add(&mut s.c, &s.c, &s.d);
+            gmp::mpz_mul_ui(&mut s.t, &s.t, k * k);
+            gmp::mpz_add(&mut s.t, &s.t, &p);
+            gmp::mpz_mul_ui(&mut s.u, &s.u, k * k);
+            gmp::mpz_mul_ui(&mut s.u, &s.u, k);
+            gmp::mpz_addmul(&mut s.u, &p, &s.c);
+            gmp::mpz_mul_ui(&mut s.d, &s.d, k);
+            let k2 = (k * k) as u128;
+            q_acc = match q_acc.checked_mul(k2) {
+                Some(v) => v,
+                None => {
+                    mpz_mul_u128(&mut s.q, q_acc, &mut tmp);
+                    k2
+                }
+            };
+        }
+        mpz_mul_u128(&mut s.q, q_acc, &mut tmp);
+        gmp::mpz_clear(&mut p);
+        gmp::mpz_clear(&mut tmp);
+    }
+    s
+}
+
+// b * c, in a task of its own if spawn is set, so products joined together run in
+// parallel
+async fn mul(wrap: WrappedMpzTri, spawn: bool) -> WrappedMpz {
+    let product = move || {
+        let mut wrap = wrap;
+        unsafe {
+            gmp::mpz_mul(&mut wrap.a, &wrap.b, &wrap.c);
+        }
+        WrappedMpz { a: wrap.a }
+    };
+    if spawn {
+        tokio::spawn(async move { product() }).await.unwrap()
+    } else {
+        product()
+    }
+}
+
+fn operands(b: mpz_t, c: mpz_t) -> WrappedMpzTri {
+    WrappedMpzTri {
+        a: allocate_mpz(0),
+        b,
+        c,
+    }
+}
+
+// the sums over (n1, n2] from those over (n1, m] and (m, n2], with pl = n^(2 (m - n1)):
+//   q = ql qr, d = dl dr, c = cl dr + cr dl, t = tl qr + pl tr,
+//   u = ul qr dr + pl (dl ur + cl dr tr)
+async fn merge(mut l: Sums, mut r: Sums, mut pl: WrappedMpz, spawn: bool) -> Sums {
+    let (q, d, cl_dr, mut cr_dl, tl_qr, mut pl_tr, mut qr_dr, dl_ur) = tokio::join!(
+        mul(operands(l.q, r.q), spawn),
+        mul(operands(l.d, r.d), spawn),
+        mul(operands(l.c, r.d), spawn),
+        mul(operands(r.c, l.d), spawn),
+        mul(operands(l.t, r.q), spawn),
+        mul(operands(pl.a, r.t), spawn),
+        mul(operands(r.q, r.d), spawn),
+        mul(operands(l.d, r.u), spawn),
+    );
+    let (mut cl_dr_tr, mut u_1) = tokio::join!(
+        mul(operands(cl_dr.a, r.t), spawn),
+        mul(operands(l.u, qr_dr.a), spawn)
+    );
+    let mut s = Sums {
+        q: q.a,
+        d: d.a,
+        c: cl_dr.a,
+        t: tl_qr.a,
+        u: dl_ur.a,
+    };
+    unsafe {
+        gmp::mpz_add(&mut s.c, &s.c, &cr_dl.a);
+        gmp::mpz_add(&mut s.t, &s.t, &pl_tr.a);
+        // dl ur + cl dr tr, the product with pl still to come
+        gmp::mpz_add(&mut s.u, &s.u, &cl_dr_tr.a);
+    }
+    let mut u_2 = mul(operands(pl.a, s.u), spawn).await;
+    unsafe {
+        gmp::mpz_add(&mut s.u, &u_1.a, &u_2.a);
+        for z in [
+            &mut pl,
+            &mut cr_dl,
+            &mut pl_tr,
+            &mut qr_dr,
+            &mut cl_dr_tr,
+            &mut u_1,
+            &mut u_2,
+        ] {
+            gmp::mpz_clear(&mut z.a);
+        }
+        l.clear();
+        r.clear();
+    }
+    s
+}
+
+// leaves of LEAF_TERMS terms, and everything from THRESH terms up split into tasks,
+// like series::compute_pqt
+#[async_recursion::async_recursion]
+async fn compute_sums(n: u64, n1: u64, n2: u64) -> Sums {
+    if n2 - n1 <= LEAF_TERMS {
+        return leaf_sums(n, n1, n2);
+    }
+    let m = (n1 + n2) / 2;
+    let spawn = n2 - n1 >= THRESH;
+    let (l, r) = if spawn {
+        let l_hook = tokio::spawn(compute_sums(n, n1, m));
+        let r_hook = tokio::spawn(compute_sums(n, m, n2));
+        (l_hook.await.unwrap(), r_hook.await.unwrap())
+    } else {
+        (compute_sums(n, n1, m).await, compute_sums(n, m, n2).await)
+    };
+    let mut pl = WrappedMpz { a: allocate_mpz(0) };
+    unsafe {
+        gmp::mpz_ui_pow_ui(&mut pl.a, n, 2 * (m - n1));
+    }
+    merge(l, r, pl, spawn).await
+}
+
+pub async fn compute_gamma(digits: u64, prec: u64) -> WrappedMpf {
+    // pi e^(-4n) < 10^-(digits + 1)
+    let target = ((digits + 1) as f64 * 10f64.ln() + std::f64::consts::PI.ln()) / 4.0;
+    let n = smooth_at_least((target.ceil() as u64).max(2));
+    let k = terms_for_digits(n, digits);
+    eprintln!("gamma: n = {n}, {k} terms");
+    // ln(n) only depends on n, so it runs alongside the sums
+    let ln_hook = tokio::spawn(compute_ln(n, digits, prec));
+    let start = Instant::now();
+    let mut s = compute_sums(n, 0, k).await;
+    manifest::record_terms(k);
+    manifest::record_phase("brent-mcmillan sums", start);
+    eprintln!("sums done");
+    unsafe {
+        // the k = 0 term adds 1 to the plain sum and nothing to the harmonic one, so
+        // gamma = (U / (Q D)) / (1 + T / Q) - ln(n) = U / (D (Q + T)) - ln(n)
+        gmp::mpz_add(&mut s.t, &s.t, &s.q);
+        gmp::mpz_mul(&mut s.d, &s.d, &s.t);
+        let num_handle = tokio::spawn(mpf_cast(WrappedMpz { a: s.u }, prec));
+        let den_handle = tokio::spawn(mpf_cast(WrappedMpz { a: s.d }, prec));
+        let (num, den) = tokio::join!(num_handle, den_handle);
+        let (mut num, mut den) = (num.unwrap().a, den.unwrap().a);
+        s.clear();
+        let mut gamma = allocate_mpf(0, prec);
+        gmp::mpf_div(&mut gamma, &num, &den);
+        let mut ln = ln_hook.await.unwrap().a;
+        gmp::mpf_sub(&mut gamma, &gamma, &ln);
+        gmp::mpf_clear(&mut num);
+        gmp::mpf_clear(&mut den);
+        gmp::mpf_clear(&mut ln);
+        WrappedMpf { a: gamma }
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::time::Instant;
+
+use crate::flag_value;
+use crate::source::{CHUNK, Source};
+
+// index files: every k digit string's positions, bucketed by its value
+//   magic    8 bytes  "PITHIDX1"
+//   k        u32
+//   width    u32      bytes per stored position, 4 or 8
+//   digits   u64      digits indexed
+//   offset   u64      position of the first of them
+//   starts   (10^k + 1) u64, where each bucket starts in the position list
+//   positions, ascending within each bucket
+const MAGIC: &[u8; 8] = b"PITHIDX1";
+const HEADER_LEN: u64 = 8 + 4 + 4 + 8 + 8;
+const DEFAULT_K: u32 = 6;
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(b)
+}
+
+// calls f(position, value) for every k digit window of the file, in order
+fn for_each_window(path: &str, k: u32, mut f: impl FnMut(u64, usize)) -> (u64, u64) {
+    let mut source = Source::open(path);
+    let modulus = 10usize.pow(k);
+    let (mut value, mut seen) = (0usize, 0u64);
+    let mut buf = Vec::new();
+    loop {
+        source.read_chunk(&mut buf, CHUNK);
+        if buf.is_empty() {
+            break;
+        }
+        for &d in buf.iter() {
+            assert!(d.is_ascii_digit(), "index only supports decimal digits");
+            value = (value * 10 + (d - b'0') as usize) % modulus;
+            seen += 1;
+            if seen >= k as u64 {
+                f(source.offset + seen - k as u64, value);
+            }
+        }
+    }
+    (seen, source.offset)
+}
+
+// 10^7 + 1 bucket starts is 80 MB already, past that the table outgrows the digits
+const MAX_K: u32 = 7;
+// bytes of positions held at once while building; each more takes another pass
+const BUILD_MEMORY: usize = 1 << 28;
+
+// one pass to size the buckets, then one per run of buckets whose positions fit in
+// memory bytes, each appended to the file as it's filled
+fn write_index(path: &str, k: u32, out_path: &str, memory: usize) -> u64 {
+    let buckets = 10usize.pow(k);
+    let mut starts = vec![0u64; buckets + 1];
+    let (digits, offset) = for_each_window(path, k, |_, v| starts[v + 1] += 1);
+    for i in 0..buckets {
+        starts[i + 1] += starts[i];
+    }
+    let width: usize = if offset + digits <= u32::MAX as u64 {
+        4
+    } else {
+        8
+    };
+
+    let mut out = BufWriter::new(File::create(out_path).unwrap());
+    out.write_all(MAGIC).unwrap();
+    out.write_all(&k.to_le_bytes()).unwrap();
+    out.write_all(&(width as u32).to_le_bytes()).unwrap();
+    out.write_all(&digits.to_le_bytes()).unwrap();
+    out.write_all(&offset.to_le_bytes()).unwrap();
+    for s in starts.iter() {
+        out.write_all(&s.to_le_bytes()).unwrap();
+    }
+    let mut lo = 0;
+    while lo < buckets {
+        // at least one bucket per pass, however big it is
+        let mut hi = lo + 1;
+        while hi < buckets && (starts[hi + 1] - starts[lo]) as usize * width <= memory {
+            hi += 1;
+        }
+        let base = starts[lo];
+        let mut next = starts[lo..hi].to_vec();
+        let mut positions = vec![0u8; (starts[hi] - base) as usize * width];
+        for_each_window(path, k, |pos, v| {
+            if (lo..hi).contains(&v) {
+                let at = (next[v - lo] - base) as usize * width;
+                positions[at..at + width].copy_from_slice(&pos.to_le_bytes()[..width]);
+                next[v - lo] += 1;
+            }
+        });
+        out.write_all(&positions).unwrap();
+        lo = hi;
+    }
+    out.flush().unwrap();
+    digits
+}
+
+// pi-thing index <digit file> [--k <k>] [--output <file>]
+pub fn build(args: &[String]) {
+    let path = &args[0];
+    let k = flag_value(args, "--k").map_or(DEFAULT_K, |k| k.parse::<u32>().unwrap());
+    assert!((1..=MAX_K).contains(&k), "k has to be 1..={MAX_K}");
+    let out_path = flag_value(args, "--output").map_or(format!("{path}.idx"), str::to_string);
+    let start = Instant::now();
+    let digits = write_index(path, k, &out_path, BUILD_MEMORY);
+    println!(
+        "indexed {digits} digits, k = {k}, into {out_path} ({:.3}s)",
+        start.elapsed().as_secs_f64()
+    );
+}
+
+struct Index {
+    file: File,
+    k: u32,
+    width: usize,
+}
+
+impl Index {
+    fn open(path: &str) -> Index {
+        let mut file = File::open(path).unwrap();
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut header).unwrap();
+        assert!(&header[..8] == MAGIC, "{path} is not an index");
+        Index {
+            file,
+            k: le_u64(&header[8..12]) as u32,
+            width: le_u64(&header[12..16]) as usize,
+        }
+    }
+
+    // the ascending positions of the k digit string with this value
+    fn bucket(&mut self, value: usize) -> Vec<u64> {
+        let mut range = [0u8; 16];
+        self.file
+            .seek(SeekFrom::Start(HEADER_LEN + 8 * value as u64))
+            .unwrap();
+        self.file.read_exact(&mut range).unwrap();
+        let (b1, b2) = (le_u64(&range[..8]), le_u64(&range[8..]));
+        let table = HEADER_LEN + 8 * (10u64.pow(self.k) + 1);
+        let mut raw = vec![0u8; (b2 - b1) as usize * self.width];
+        self.file
+            .seek(SeekFrom::Start(table + b1 * self.width as u64))
+            .unwrap();
+        self.file.read_exact(&mut raw).unwrap();
+        raw.chunks(self.width).map(le_u64).collect()
+    }
+}
+
+// first position of pattern: candidates from the bucket of its first k digits, each
+// checked against the digit file. patterns shorter than k are scanned for instead,
+// they turn up within the first few 10^len digits anyway
+fn first_position(index: &mut Index, digits: &mut Source, pattern: &[u8]) -> Option<u64> {
+    let k = index.k as usize;
+    if pattern.len() < k {
+        let mut buf = Vec::new();
+        let mut carry: Vec<u8> = Vec::new();
+        let mut pos = digits.offset;
+        loop {
+            digits.read_chunk(&mut buf, CHUNK);
+            if buf.is_empty() {
+                return None;
+            }
+            let mut block = std::mem::take(&mut carry);
+            block.extend_from_slice(&buf);
+            if let Some(i) = block.windows(pattern.len()).position(|w| w == pattern) {
+                return Some(pos + i as u64);
+            }
+            carry = block[block.len().saturating_sub(pattern.len() - 1)..].to_vec();
+            pos += (block.len() - carry.len()) as u64;
+        }
+    }
+    let value = pattern[..k]
+        .iter()
+        .fold(0usize, |v, &d| v * 10 + (d - b'0') as usize);
+    index
+        .bucket(value)
+        .into_iter()
+        .find(|&p| pattern.len() == k || digits.read_at(p, pattern.len()) == pattern)
+}
+
+// pi-thing lookup <digit file> <pattern>... [--index <file>]
+pub fn lookup(args: &[String]) {
+    let path = &args[0];
+    let index_path = flag_value(args, "--index").map_or(format!("{path}.idx"), str::to_string);
+    let mut index = Index::open(&index_path);
+    let mut patterns = args[1..].iter();
+    while let Some(p) = patterns.next() {
+        if p == "--index" {
+            patterns.next();
+            continue;
+        }
+        assert!(
+            !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()),
+            "patterns are decimal digits"
+        );
+        let start = Instant::now();
+        // a fresh source each time, the short pattern scan reads from the start
+        let mut digits = Source::open(path);
+        let found = first_position(&mut index, &mut digits, p.as_bytes());
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        match found {
+            Some(pos) => println!("{p}: {pos} ({ms:.3} ms)"),
+            None => println!("{p}: not found ({ms:.3} ms)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::search_stream;
+    use crate::temp_path;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lookup_agrees_with_search_on_wrapped_text() {
+        let digits: Vec<u8> = (0..20000u64)
+            .map(|i| b'0' + (i * i % 97 % 10) as u8)
+            .collect();
+        let (path, index_path) = (temp_path("wrapped.txt"), temp_path("wrapped.idx"));
+        let mut text = Vec::new();
+        for line in digits.chunks(61) {
+            text.extend_from_slice(line);
+            text.extend_from_slice(b"\r\n");
+        }
+        std::fs::write(&path, &text).unwrap();
+        // a few kilobytes of positions at a time, so the buckets take several passes
+        assert_eq!(write_index(&path, 3, &index_path, 4096), 20000);
+        let mut index = Index::open(&index_path);
+
+        for start in [0, 59, 60, 61, 4095, 4096, 12345, 19990] {
+            for len in [2, 3, 7] {
+                let pattern = &digits[start..start + len];
+                let mut source = Source::open(&path);
+                let (_, found) =
+                    search_stream(|buf| source.read_chunk(buf, 100), 0, pattern, 1).await;
+                let mut source = Source::open(&path);
+                let indexed = first_position(&mut index, &mut source, pattern);
+                assert_eq!(indexed, found.first().copied(), "pattern at {start}");
+                assert!(indexed.unwrap() <= start as u64);
+            }
+        }
+        assert_eq!(
+            first_position(&mut index, &mut Source::open(&path), b"12345678901234"),
+            None
+        );
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&index_path).unwrap();
+    }
+}
@@ -1,23 +1,28 @@
+mod agm;
+mod algebraic;
+mod analyze;
+mod bbp;
+mod cf;
+mod checks;
+mod chudnovsky;
+mod constants;
+mod diff;
+mod digit_at;
+mod fac;
+mod gamma;
+mod index;
+mod manifest;
+mod merkle;
+mod modarith;
+mod packed;
+mod search;
+mod series;
+mod source;
+mod verify;
+
 use core::mem::MaybeUninit;
 use gmp_mpfr_sys::{gmp, gmp::mpf_t, gmp::mpz_t};
-use std::sync::Arc;
-use std::thread;
-use std::{env, ffi::CStr};
-use tokio::sync::Mutex;
-
-const A: u64 = 13591409;
-const B: u64 = 545140134;
-const C: u64 = 640320;
-const D: u64 = 426880;
-const E: u64 = 10005;
-const C3_24: u64 = C.pow(3) / 24;
-const THRESH: u64 = 10u64.pow(4) * 5;
-
-struct PQT {
-    p: gmp::mpz_t,
-    q: gmp::mpz_t,
-    t: gmp::mpz_t,
-}
+use std::{env, ffi::CStr, time::Instant};
 
 struct WrappedMpz {
     a: gmp::mpz_t,
@@ -44,7 +49,6 @@ struct WrappedMpfTri {
     c: gmp::mpf_t,
 }
 
-unsafe impl Send for PQT {} // luckily, im not accessing anything between threads
 impl Default for WrappedMpz {
     fn default() -> Self {
         WrappedMpz { a: allocate_mpz(0) }
@@ -80,21 +84,48 @@ unsafe fn allocate_mpf(init_value: u64, prec: u64) -> gmp::mpf_t {
 }
 
 fn make_cstr_mpf(fmt_str: mpf_t, digits: usize) -> String {
+    make_cstr_mpf_exp(fmt_str, digits, 10).0
+}
+
+// the digits and the exponent, the value being 0.<digits> * base^exponent. zero has no
+// digits at all
+fn make_cstr_mpf_exp(fmt_str: mpf_t, digits: usize, base: i32) -> (String, i64) {
     let mut expptr: i64 = 0;
     unsafe {
-        CStr::from_ptr(gmp::mpf_get_str(
+        let s = CStr::from_ptr(gmp::mpf_get_str(
             std::ptr::null_mut(),
             &mut expptr,
-            10i32,
+            base,
             digits,
             &fmt_str as *const mpf_t,
         ))
         .to_str()
         .unwrap()
-        .to_string()
+        .to_string();
+        (s, expptr)
     }
 }
 
+// digits from make_cstr_mpf_exp with the point put back where it belongs
+fn with_point(digits: &str, exponent: i64) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(d) => ("-", d),
+        None => ("", digits),
+    };
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    let body = if exponent <= 0 {
+        format!("0.{}{digits}", "0".repeat(exponent.unsigned_abs() as usize))
+    } else if exponent as usize >= digits.len() {
+        format!("{digits}{}", "0".repeat(exponent as usize - digits.len()))
+    } else {
+        let (int, frac) = digits.split_at(exponent as usize);
+        format!("{int}.{frac}")
+    };
+    format!("{sign}{body}")
+}
+
 fn make_cstr_mpz(fmt_str: mpz_t) -> String {
     unsafe {
         CStr::from_ptr(gmp::mpz_get_str(
@@ -108,144 +139,6 @@ fn make_cstr_mpz(fmt_str: mpz_t) -> String {
     }
 }
 
-async fn calc_sqrt(prec: u64) -> WrappedMpf {
-    unsafe {
-        let mut e = allocate_mpf(E, prec);
-        gmp::mpf_sqrt(&mut e as *mut mpf_t, &e as *const mpf_t);
-        println!("e done");
-        WrappedMpf { a: e }
-    }
-}
-
-async fn calc_sqrt_pell(prec: u64) -> WrappedMpzBi {
-    unsafe {
-        let mut xy = WrappedMpzBi {
-            a: allocate_mpz(0),
-            b: allocate_mpz(0),
-        };
-        let mut p1 = WrappedMpzBi {
-            a: allocate_mpz(1u64),
-            b: allocate_mpz(0u64),
-        };
-        let mut p2 = WrappedMpzBi {
-            a: allocate_mpz(4001u64),
-            b: allocate_mpz(40u64),
-        };
-        let mut tmp = WrappedMpz { a: allocate_mpz(0) };
-        let mut target = WrappedMpz {
-            a: allocate_mpz(10),
-        };
-        gmp::mpz_pow_ui(
-            &mut target.a as *mut mpz_t,
-            &target.a as *const mpz_t,
-            (prec / 2) + 5,
-        );
-        loop {
-            // x = x1*x2 + D*y1*y2
-            let x1_wrap = WrappedMpz { a: p1.a };
-            let x2_wrap = WrappedMpz { a: p2.a };
-            let x_1_c_handle = tokio::spawn(async {
-                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
-                let x1 = x1_wrap;
-                let x2 = x2_wrap;
-                gmp::mpz_mul(
-                    &mut tmp.a as *mut mpz_t,
-                    &x1.a as *const mpz_t,
-                    &x2.a as *const mpz_t,
-                );
-                WrappedMpz { a: tmp.a }
-            });
-            let y1_wrap = WrappedMpz { a: p1.b };
-            let y2_wrap = WrappedMpz { a: p2.b };
-            let x_2_c_handle = tokio::spawn(async {
-                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
-                let y1 = y1_wrap;
-                let y2 = y2_wrap;
-                gmp::mpz_mul(
-                    &mut tmp.a as *mut mpz_t,
-                    &y1.a as *const mpz_t,
-                    &y2.a as *const mpz_t,
-                );
-                gmp::mpz_mul_ui(&mut tmp.a as *mut mpz_t, &tmp.a as *const mpz_t, E);
-                WrappedMpz { a: tmp.a }
-            });
-
-            // y = x1*y2 + y1*x2
-            let x1_wrap = WrappedMpz { a: p1.a };
-            let y2_wrap = WrappedMpz { a: p2.b };
-            let y_1_c_handle = tokio::spawn(async {
-                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
-                let x1 = x1_wrap;
-                let y2 = y2_wrap;
-                gmp::mpz_mul(
-                    &mut tmp.a as *mut mpz_t,
-                    &x1.a as *const mpz_t,
-                    &y2.a as *const mpz_t,
-                );
-                WrappedMpz { a: tmp.a }
-            });
-            let y1_wrap = WrappedMpz { a: p1.b };
-            let x2_wrap = WrappedMpz { a: p2.a };
-            let y_2_c_handle = tokio::spawn(async {
-                let mut tmp = WrappedMpz { a: allocate_mpz(0) };
-                let y1 = y1_wrap;
-                let x2 = x2_wrap;
-                gmp::mpz_mul(
-                    &mut tmp.a as *mut mpz_t,
-                    &y1.a as *const mpz_t,
-                    &x2.a as *const mpz_t,
-                );
-                WrappedMpz { a: tmp.a }
-            });
-            //(x, y) = {
-            let x_handle = tokio::spawn(async move {
-                //let x_1_c = x_1_c_handle.await.unwrap();
-                //let x_2_c = x_2_c_handle.await.unwrap();
-                let (x_1_c, x_2_c) = tokio::join!(x_1_c_handle, x_2_c_handle);
-                let tmp = WrappedMpz { a: allocate_mpz(0) };
-                //gmp::mpz_add(
-                //    &mut tmp as *mut mpz_t,
-                //    &x_1_c.a as *const mpz_t,
-                //    &x_2_c.a as *const mpz_t,
-                //);
-                mpz_add_ns(WrappedMpz { a: tmp.a }, x_1_c.unwrap(), x_2_c.unwrap())
-                //WrappedMpz { a: tmp }
-            });
-            let y_handle = tokio::spawn(async move {
-                //let y_1_c = y_1_c_handle.await.unwrap();
-                //let y_2_c = y_2_c_handle.await.unwrap();
-                let (y_1_c, y_2_c) = tokio::join!(y_1_c_handle, y_2_c_handle);
-                let tmp = WrappedMpz { a: allocate_mpz(0) };
-                //gmp::mpz_add(
-                //    &mut tmp as *mut mpz_t,
-                //    &y_1_c.a as *const mpz_t,
-                //    &y_2_c.a as *const mpz_t,
-                //);
-                mpz_add_ns(WrappedMpz { a: tmp.a }, y_1_c.unwrap(), y_2_c.unwrap())
-                //WrappedMpz { a: tmp }
-            });
-            // let y_h = y_handle.await;
-            //let x_r = x_handle.await.unwrap().a;
-            //let y_r = y_handle.await.unwrap().a;
-            let (x_r, y_r) = tokio::join!(x_handle, y_handle);
-            gmp::mpz_set(&mut xy.a as *mut mpz_t, &x_r.unwrap().a as *const mpz_t);
-            gmp::mpz_set(&mut xy.b as *mut mpz_t, &y_r.unwrap().a as *const mpz_t);
-            //(x, y)
-            //};
-            if gmp::mpz_cmp(&xy.b as *const mpz_t, &target.a as *const mpz_t) > 0 {
-                // should dealloc memory here
-                break;
-            }
-            gmp::mpz_set(&mut p1.a as *mut mpz_t, &p2.a as *const mpz_t);
-            gmp::mpz_set(&mut p1.b as *mut mpz_t, &p2.b as *const mpz_t);
-            gmp::mpz_set(&mut p2.a as *mut mpz_t, &xy.a as *const mpz_t);
-            gmp::mpz_set(&mut p2.b as *mut mpz_t, &xy.b as *const mpz_t);
-        }
-        println!("e done");
-        WrappedMpzBi { a: xy.a, b: xy.b }
-    }
-}
-
 async fn mpf_mul(mut wrap: WrappedMpfTri) -> WrappedMpf {
     unsafe {
         gmp::mpf_mul(
@@ -257,6 +150,7 @@ async fn mpf_mul(mut wrap: WrappedMpfTri) -> WrappedMpf {
     }
 }
 
+#[allow(dead_code)]
 async fn mpf_add(mut wrap: WrappedMpfTri) -> WrappedMpf {
     unsafe {
         gmp::mpf_add(
@@ -287,356 +181,227 @@ async fn mpf_cast(wrap: WrappedMpz, prec: u64) -> WrappedMpf {
     }
 }
 
-fn i_compute_pqt(n1: u64, n2: u64) -> PQT {
-    // async unsafe fn compute_pqt(a: mpz_t, b: mpz_t, c3_24: mpz_t, n1: u64, n2: u64) -> PQT {
-    // println!("compute_pqt called {} {}", n1, n2);
-    let mut pqt: PQT = PQT {
-        p: allocate_mpz(0),
-        q: allocate_mpz(0),
-        t: allocate_mpz(0),
-    };
-    unsafe {
-        if n1 + 1 == n2 {
-            let p_mut = &mut pqt.p as *mut mpz_t;
-            let p_const = &pqt.p as *const mpz_t;
-            gmp::mpz_set_ui(p_mut, 2 * n2 - 1);
-            gmp::mpz_mul_ui(p_mut, p_const, 6 * n2 - 1);
-            gmp::mpz_mul_ui(p_mut, p_const, 6 * n2 - 5);
-            let q_mut = &mut pqt.q as *mut mpz_t;
-            let q_const = &pqt.q as *const mpz_t;
-            gmp::mpz_set_ui(q_mut, C3_24);
-            let mut n2_3 = allocate_mpz(n2);
-            gmp::mpz_set_ui(&mut pqt.t as *mut mpz_t, A);
-            gmp::mpz_addmul_ui(&mut pqt.t as *mut mpz_t, &n2_3 as *const mpz_t, B);
-            gmp::mpz_mul(
-                &mut pqt.t as *mut mpz_t,
-                &pqt.t as *const mpz_t,
-                &pqt.p as *const mpz_t,
-            );
-            if (n2 & 1) == 1 {
-                gmp::mpz_neg(&mut pqt.t as *mut mpz_t, &pqt.t as *const mpz_t);
-            }
-            gmp::mpz_ui_pow_ui(&mut n2_3 as *mut mpz_t, n2, 3);
-            gmp::mpz_mul(q_mut, q_const, &n2_3 as *const mpz_t);
-            gmp::mpz_clear(&mut n2_3);
-        } else {
-            let m = (n1 + n2) / 2;
-            // single thread
-            let mut res1 = i_compute_pqt(n1, m); // res1 is used as a temp buffer to reduce mem
-            let mut res2 = i_compute_pqt(m, n2);
-            gmp::mpz_mul(
-                &mut pqt.p as *mut mpz_t,
-                &res1.p as *const mpz_t,
-                &res2.p as *const mpz_t,
-            );
-            gmp::mpz_mul(
-                &mut pqt.q as *mut mpz_t,
-                &res1.q as *const mpz_t,
-                &res2.q as *const mpz_t,
-            );
-            let mut t_1 = allocate_mpz(0);
-            let mut t_2 = allocate_mpz(0);
-            gmp::mpz_mul(
-                &mut t_1 as *mut mpz_t,
-                &res1.t as *const mpz_t,
-                &res2.q as *const mpz_t,
-            );
-            gmp::mpz_mul(
-                &mut t_2 as *mut mpz_t,
-                &res1.p as *const mpz_t,
-                &res2.t as *const mpz_t,
-            );
-            gmp::mpz_add(
-                &mut pqt.t as *mut mpz_t,
-                &t_1 as *const mpz_t,
-                &t_2 as *const mpz_t,
-            );
+// value following --name, if it was given
+fn flag_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
 
-            gmp::mpz_clear(&mut res1.p);
-            gmp::mpz_clear(&mut res1.q);
-            gmp::mpz_clear(&mut res1.t);
-            gmp::mpz_clear(&mut res2.p);
-            gmp::mpz_clear(&mut res2.q);
-            gmp::mpz_clear(&mut res2.t);
-            gmp::mpz_clear(&mut t_1);
-            gmp::mpz_clear(&mut t_2);
+// a path in the temp directory that no other test or test run uses
+#[cfg(test)]
+fn temp_path(name: &str) -> String {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("pi-thing-test-{}-{name}", std::process::id()));
+    path.to_str().unwrap().to_string()
+}
+
+// bits needed for digits decimal digits. 64 guard bits so the printed digits only
+// depend on T / Q, not on how it was scaled
+fn prec_for_digits(digits: u64) -> u64 {
+    (digits as f64 * 10f64.log2()) as u64 + 64
+}
+
+async fn compute(constant: &str, algorithm: &str, digits: u64, prec: u64) -> mpf_t {
+    match constant {
+        "pi" => match algorithm {
+            "chudnovsky" => chudnovsky::compute_pi(digits, prec).await.a,
+            "agm" => agm::compute_pi(prec).await.a,
+            "machin" => {
+                constants::compute_pi_atan(&constants::MACHIN, digits, prec)
+                    .await
+                    .a
+            }
+            "takano" => {
+                constants::compute_pi_atan(&constants::TAKANO, digits, prec)
+                    .await
+                    .a
+            }
+            "stormer" => {
+                constants::compute_pi_atan(&constants::STORMER, digits, prec)
+                    .await
+                    .a
+            }
+            _ => panic!("unknown algorithm {algorithm}"),
+        },
+        "e" => constants::compute_e(digits, prec).await.a,
+        "zeta3" => constants::compute_zeta3(digits, prec).await.a,
+        "catalan" => constants::compute_catalan(digits, prec).await.a,
+        "gamma" => gamma::compute_gamma(digits, prec).await.a,
+        "phi" => algebraic::compute_phi(prec).await.a,
+        _ if constant.starts_with("sqrt") => {
+            algebraic::compute_root(&constant[4..], 2, prec).await.a
         }
+        _ if constant.starts_with("cbrt") => {
+            algebraic::compute_root(&constant[4..], 3, prec).await.a
+        }
+        _ => match constant.strip_prefix("ln").map(|n| n.parse::<u64>()) {
+            Some(Ok(n)) => constants::compute_ln(n, digits, prec).await.a,
+            _ => panic!("unknown constant {constant}"),
+        },
     }
-    pqt
 }
 
-#[async_recursion::async_recursion]
-async fn compute_pqt(n1: u64, n2: u64) -> PQT {
-    // async unsafe fn compute_pqt(a: mpz_t, b: mpz_t, c3_24: mpz_t, n1: u64, n2: u64) -> PQT {
-    // println!("compute_pqt called {} {}", n1, n2);
-    if n1 + 1 == n2 {
-        return i_compute_pqt(n1, n2);
+// what actually computes constant, for the manifest
+fn algorithm_name<'a>(constant: &str, algorithm: &'a str) -> &'a str {
+    match constant {
+        "pi" => algorithm,
+        "e" => "factorial series",
+        "zeta3" => "amdeberhan-zeilberger series",
+        "catalan" => "lupas series",
+        "gamma" => "brent-mcmillan",
+        "phi" => "newton",
+        _ if constant.starts_with("sqrt") || constant.starts_with("cbrt") => "newton",
+        _ => "atanh machin-like formula",
     }
-    let mut pqt: PQT = PQT {
-        p: allocate_mpz(0),
-        q: allocate_mpz(0),
-        t: allocate_mpz(0),
+}
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 50)]
+async fn main() {
+    // pi-thing <digits> [--constant pi|e|ln<n>|zeta3|catalan|gamma|phi|sqrt<a[/b]>|cbrt<a[/b]>]
+    //                  [--algorithm chudnovsky|agm|machin|takano|stormer] [--hex] [--check]
+    //                  [--output <file> [--format text|packed] [--merkle [--block-size <n>]]]
+    // pi-thing verify --digits <n> [--algorithm agm|machin|takano|stormer]
+    // pi-thing bbp --position <n> [--count <k>]
+    // pi-thing digit-at --position <n> [--count <k>]
+    // pi-thing selftest [--max-digits <n>]
+    // pi-thing verify-range <file> --tree <file> [--start <a>] [--end <b>] [--offset <o>]
+    // pi-thing pack <file> --output <file> [--constant <name>] [--offset <o>] [--exponent <e>]
+    //                [--hex]
+    // pi-thing unpack <file> [--start <a>] [--end <b>] [--output <file>]
+    // pi-thing diff <file> <file>
+    // pi-thing search <pattern> [--file <file> | --digits <n> [--constant <name>]]
+    // pi-thing index <file> [--k <k>] [--output <file>]
+    // pi-thing lookup <file> <pattern>... [--index <file>]
+    // pi-thing analyze [--file <file> | --digits <n> [--constant <name>]] [--window <w>]
+    //                  [--output <file>]
+    // pi-thing cf --digits <n> [--constant <name>] [--terms <k>] [--convergents <k>]
+    let args: Vec<String> = env::args().skip(1).collect();
+    // any mode: --check verifies every binary splitting merge mod a few primes
+    if args.iter().any(|a| a == "--check") {
+        series::enable_checks();
+    }
+    match args[0].as_str() {
+        "verify" => return verify::run(&args[1..]).await,
+        "bbp" => return bbp::run(&args[1..]).await,
+        "digit-at" => return digit_at::run(&args[1..]).await,
+        "selftest" => return checks::selftest(&args[1..]).await,
+        "verify-range" => return merkle::verify_range(&args[1..]),
+        "pack" => return packed::pack(&args[1..]),
+        "unpack" => return packed::unpack(&args[1..]),
+        "diff" => return diff::run(&args[1..]),
+        "search" => return search::run(&args[1..]).await,
+        "index" => return index::build(&args[1..]),
+        "lookup" => return index::lookup(&args[1..]),
+        "analyze" => return analyze::run(&args[1..]).await,
+        "cf" => return cf::run(&args[1..]).await,
+        _ => {}
+    }
+    let digits = args[0].parse::<u32>().unwrap();
+    let constant = flag_value(&args, "--constant").unwrap_or("pi");
+    // gamma's sums are split by gamma.rs itself, not by the engine the checks are in
+    assert!(
+        !(constant == "gamma" && args.iter().any(|a| a == "--check")),
+        "--check doesn't cover gamma"
+    );
+    // only pi has more than one algorithm
+    let algorithm = flag_value(&args, "--algorithm").unwrap_or("chudnovsky");
+    println!("Computing {} digits of {}", digits, constant);
+    let prec = prec_for_digits(digits as u64);
+    let start = Instant::now();
+    let value = compute(constant, algorithm, digits as u64, prec).await;
+    manifest::record_phase("compute", start);
+    println!("computed, making string");
+    let start = Instant::now();
+    let hex = args.iter().any(|a| a == "--hex");
+    let (printout, exponent) = if hex {
+        // as many hex digits as the decimal digits are worth
+        make_cstr_mpf_exp(value, (digits as f64 / 16f64.log10()) as usize, 16)
+    } else {
+        make_cstr_mpf_exp(value, digits as usize, 10)
     };
-    let t_1 = WrappedMpz { a: allocate_mpz(0) };
-    let t_2 = WrappedMpz { a: allocate_mpz(0) };
-    unsafe {
-        let m = (n1 + n2) / 2;
-        let mut res1: PQT;
-        let mut res2: PQT;
-        if n2 - n1 < THRESH {
-            res1 = i_compute_pqt(n1, m);
-            res2 = i_compute_pqt(m, n2);
-        } else {
-            // single thread
-            //let mut res1 = compute_pqt(n1, m).await; // res1 is used as a temp buffer to reduce mem
-            //let mut res2 = compute_pqt(m, n2).await;
-            // multi thread
-            let res1_hook = tokio::spawn(compute_pqt(n1, m));
-            let res2_hook = tokio::spawn(compute_pqt(m, n2));
-            res1 = res1_hook.await.unwrap();
-            res2 = res2_hook.await.unwrap();
+    manifest::record_phase("to string", start);
+    let output = flag_value(&args, "--output");
+    match output {
+        Some(path) => {
+            let start = Instant::now();
+            match flag_value(&args, "--format").unwrap_or("text") {
+                "text" => std::fs::write(path, &printout).unwrap(),
+                "packed" => {
+                    let header = packed::Header {
+                        constant: constant.to_string(),
+                        radix: if hex { 16 } else { 10 },
+                        digits: printout.len() as u64,
+                        offset: 0,
+                        exponent,
+                    };
+                    packed::write(path, &header, printout.as_bytes());
+                }
+                format => panic!("unknown format {format}"),
+            }
+            manifest::record_phase("write", start);
+            println!("digits written to {path}");
         }
-        if n2 - n1 > THRESH {
-            println!("{}", n2 - n1);
+        // pi has always been printed as bare digits. anything else can be any size, so
+        // it gets its point
+        None if constant == "pi" => println!("{printout}"),
+        None => println!("{}", with_point(&printout, exponent)),
+    }
+    let merkle_root = match output {
+        Some(path) if args.iter().any(|a| a == "--merkle") => {
+            let block = flag_value(&args, "--block-size")
+                .map_or(merkle::DEFAULT_BLOCK, |b| b.parse::<usize>().unwrap());
+            let start = Instant::now();
+            let tree_path = format!("{path}.merkle");
+            let root = merkle::write_tree(&tree_path, printout.as_bytes(), block);
+            manifest::record_phase("merkle tree", start);
+            println!("merkle tree written to {tree_path}");
+            Some(root)
         }
-        // p = res1 p * res2 p
-        let wrap_p = WrappedMpzTri {
-            a: pqt.p,
-            b: res1.p,
-            c: res2.p,
-        };
-        //let p_thread = tokio::task::spawn_blocking(move || wrap_mul(wrap_p));
-        let p_thread = tokio::spawn(async move {
-            let mut wrap = wrap_p;
-            gmp::mpz_mul(
-                &mut wrap.a as *mut mpz_t,
-                &wrap.b as *const mpz_t,
-                &wrap.c as *const mpz_t,
-            );
-            WrappedMpz { a: wrap.a }
-        });
-        // let p_thread = tokio::spawn(move || wrap_mul(wrap_p));
-        //let p_thread = tokio::spawn(move || {
-        //    wrap_mul(wrap_p);
-        //});
-        // q = res1 q * res2 q
-        let wrap_q = WrappedMpzTri {
-            a: pqt.q,
-            b: res1.q,
-            c: res2.q,
-        };
-        let q_thread = tokio::spawn(async move {
-            let mut wrap = wrap_q;
-            gmp::mpz_mul(
-                &mut wrap.a as *mut mpz_t,
-                &wrap.b as *const mpz_t,
-                &wrap.c as *const mpz_t,
-            );
-            WrappedMpz { a: wrap.a }
-        });
-        // let q_thread = tokio::spawn(move || wrap_mul(wrap_q));
-        //let q_thread = tokio::spawn(move || {
-        //    wrap_mul(wrap_q);
-        //});
-        //let mut t_1 = WrappedMpz::default();
-        //let mut t_2 = WrappedMpz::default();
-
-        //let t_1 = allocate_mpz(0);
-        let t_1_wrap = WrappedMpzTri {
-            a: t_1.a,
-            b: res1.t,
-            c: res2.q,
-        };
-        let t_1_handle = tokio::spawn(async move {
-            let mut wrap = t_1_wrap;
-            gmp::mpz_mul(
-                &mut wrap.a as *mut mpz_t,
-                &wrap.b as *const mpz_t,
-                &wrap.c as *const mpz_t,
-            );
-            WrappedMpz { a: wrap.a }
-        });
-        // let t_1_thread = tokio::spawn(move || wrap_mul(wrap_t_1));
-        //let t_1_thread = tokio::spawn(move || {
-        //    wrap_mul(t_1_wrap);
-        //});
-
-        //let t_2 = allocate_mpz(0);
-        let t_2_wrap = WrappedMpzTri {
-            a: t_2.a,
-            b: res1.p,
-            c: res2.t,
-        };
-        let t_2_handle = tokio::spawn(async move {
-            let mut wrap = t_2_wrap;
-            gmp::mpz_mul(
-                &mut wrap.a as *mut mpz_t,
-                &wrap.b as *const mpz_t,
-                &wrap.c as *const mpz_t,
-            );
-            WrappedMpz { a: wrap.a }
-        });
-        // let t_2_thread = tokio::spawn(move || wrap_mul(wrap_t_2));
-        //let t_2_thread = tokio::spawn(move || {
-        //    wrap_mul(t_2_wrap);
-        //});
-        let mut t_1 = t_1_handle.await.unwrap();
-        let mut t_2 = t_2_handle.await.unwrap();
-        gmp::mpz_add(
-            &mut pqt.t as *mut mpz_t,
-            &t_1.a as *const mpz_t,
-            &t_2.a as *const mpz_t,
-        );
-        pqt.p = p_thread.await.unwrap().a;
-        pqt.q = q_thread.await.unwrap().a;
-        gmp::mpz_clear(&mut res1.p);
-        gmp::mpz_clear(&mut res1.q);
-        gmp::mpz_clear(&mut res1.t);
-        gmp::mpz_clear(&mut res2.p);
-        gmp::mpz_clear(&mut res2.q);
-        gmp::mpz_clear(&mut res2.t);
-        gmp::mpz_clear(&mut t_1.a);
-        gmp::mpz_clear(&mut t_2.a);
+        _ => None,
+    };
+    if !hex {
+        checks::report(constant, &printout);
+    }
+    let start = Instant::now();
+    let sha256 = checks::sha256_hex(printout.as_bytes());
+    manifest::record_phase("sha256", start);
+    let manifest = manifest::Manifest {
+        constant,
+        algorithm: algorithm_name(constant, algorithm),
+        digits: digits as u64,
+        base: if hex { 16 } else { 10 },
+        exponent,
+        prec,
+        sha256,
+        merkle_root,
+    }
+    .to_json();
+    // next to the digits when they went to a file, otherwise after them
+    match output {
+        Some(path) => {
+            let manifest_path = format!("{path}.manifest.json");
+            std::fs::write(&manifest_path, manifest + "\n").unwrap();
+            println!("manifest written to {manifest_path}");
+        }
+        None => println!("{manifest}"),
     }
-    pqt
 }
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 50)]
-async fn main() {
-    let digits = env::args().nth(1).unwrap().parse::<u32>().unwrap();
-    println!("Computing {} digits", digits);
-    let prec = (digits * 10u32.ilog2()) as u64;
-    // let comp: Chudnovsky = Chudnovsky::default();
-    let digits_per_term = (53360f64.powf(3f64).ln()) / 10f64.ln();
-    let n = digits as f64 / digits_per_term;
-    unsafe {
-        let e_handle = tokio::spawn(async move { calc_sqrt_pell(digits as u64).await });
-        // let e_handle = thread::spawn(async move || calc_sqrt_pell(digits as u64));
-        let mut c3_24 = allocate_mpz(C);
-        gmp::mpz_pow_ui(&mut c3_24 as *mut mpz_t, &c3_24 as *const mpz_t, 3);
-        gmp::mpz_divexact_ui(&mut c3_24 as *mut mpz_t, &c3_24 as *const mpz_t, 24);
-        let pqt: PQT = compute_pqt(0u64, n as u64).await;
-        // let pqt: PQT = i_compute_pqt(0u64, n as u64);
-        println!("pqt done");
-        let wrap_q = WrappedMpz { a: pqt.q };
-        let wrap_t = WrappedMpz { a: pqt.t };
-        let q_handle = tokio::spawn(async move {
-            let q = mpf_cast(wrap_q, prec).await.a;
-            let wrap_q_mpf = WrappedMpf { a: q };
-            let mut q_clone = allocate_mpf(0, prec);
-            gmp::mpf_set(&mut q_clone, &q as *const mpf_t);
-            let wrap_q_mpf_clone = WrappedMpf { a: q_clone };
-            let d_handle = tokio::spawn(async move {
-                let mut d = allocate_mpf(D, prec);
-                let q = wrap_q_mpf;
-                let wrap = WrappedMpfTri { a: d, b: d, c: q.a };
-                d = mpf_mul(wrap).await.a;
-                // println!("d done");
-                WrappedMpf { a: d }
-            });
-            let a_handle = tokio::spawn(async move {
-                let mut a = allocate_mpf(A, prec);
-                let q = wrap_q_mpf_clone;
-                let wrap = WrappedMpfTri { a: a, b: a, c: q.a };
-                a = mpf_mul(wrap).await.a;
-                println!("a done");
-                WrappedMpf { a: a }
-            });
-            (d_handle, a_handle)
-        });
-        let t_handle = tokio::spawn(async move { mpf_cast(wrap_t, prec).await });
-        let (d_handle, a_handle) = q_handle.await.unwrap();
-        // let top_handle = tokio::spawn(async {
-        //     let e_grip = e_handle.await.unwrap();
-        //     let d_grip = d_handle.await.unwrap();
-        //     let mut e = e_grip.await.a;
-        //     let d = d_grip.a;
-        //     gmp::mpf_mul(&mut e as *mut mpf_t, &e as *const mpf_t, &d as *const mpf_t);
-        //     // println!("top done");
-        //     WrappedMpf { a: e }
-        // });
-        let bottom_handle = tokio::spawn(async {
-            let a_grip = a_handle.await.unwrap();
-            let t_grip = t_handle.await.unwrap();
-            let mut a = a_grip.a;
-            let t = t_grip;
-            // println!("a t {} {}", make_cstr_mpf(a, 100), make_cstr_mpf(t.a, 100));
-            gmp::mpf_add(
-                &mut a as *mut mpf_t,
-                &a as *const mpf_t,
-                &t.a as *const mpf_t,
-            );
-            // println!("bottom done");
-            WrappedMpf { a: a }
-        });
-        // let e_comp = e_handle.join().unwrap().await.await;
-        let e_comp = e_handle.await.unwrap();
-        let e_f = e_comp;
-        let e_x = WrappedMpz { a: e_f.a };
-        let e_y = WrappedMpz { a: e_f.b };
-        // println!("{} {}", make_cstr_mpz(e_x.a), make_cstr_mpz(e_y.a));
-        let top_handle = tokio::spawn(async {
-            let e_x_mpf = mpf_cast(e_x, 0u64).await;
-            let d_grip = d_handle.await.unwrap();
-            let mut d = d_grip.a;
-            gmp::mpf_mul(
-                &mut d as *mut mpf_t,
-                &e_x_mpf.a as *const mpf_t,
-                &d as *const mpf_t,
-            );
-            // println!("top done");
-            WrappedMpf { a: d }
-        });
-        let mut pi = allocate_mpf(0, prec);
-        let bottom_mul_handle = tokio::spawn(async {
-            let e_y_mpf = mpf_cast(e_y, 0u64).await;
-            let mut bottom = bottom_handle.await.unwrap().a;
-            gmp::mpf_mul(
-                &mut bottom as *mut mpf_t,
-                &bottom as *const mpf_t,
-                &e_y_mpf.a as *const mpf_t,
-            );
-            WrappedMpf { a: bottom }
-        });
-        let top = top_handle.await.unwrap().a;
-        let bottom = bottom_mul_handle.await.unwrap().a;
-        // println!("meow {}", make_cstr_mpf(bottom, 100));
-        gmp::mpf_div(
-            &mut pi as *mut mpf_t,
-            &top as *const mpf_t,
-            &bottom as *const mpf_t,
-        );
-        //println!("casts done");
-        //gmp::mpf_sqrt(&mut e as *mut mpf_t, &e as *const mpf_t);
-        //println!("sqrt done");
-        //gmp::mpf_mul(&mut e as *mut mpf_t, &e as *const mpf_t, &q as *const mpf_t);
-        // let d_hook = tokio::spawn(async { mpf_mul(WrappedMpfTri { a: d, b: d, c: q }) });
-        // gmp::mpf_mul(
-        //     &mut d as *mut mpf_t,
-        //     &d as *const mpf_t,
-        //     &pqt.t as *const mpf_t,
-        // );
-        // println!("mul1 done");
-        // gmp::mpf_mul(
-        //     &mut pi as *mut mpf_t,
-        //     &e as *const mpf_t,
-        //     &allocate_mpf(D, prec) as *const mpf_t, // d
-        // );
-        // println!("mul2 done");
-        // // e is not representative here. just saving mem
-        // gmp::mpf_mul(
-        //     &mut e as *mut mpf_t,
-        //     &q as *const mpf_t,
-        //     &allocate_mpf(A, prec) as *const mpf_t,
-        // );
-        // gmp::mpf_add(&mut e as *mut mpf_t, &e as *const mpf_t, &t as *const mpf_t);
-        // gmp::mpf_div(
-        //     &mut pi as *mut mpf_t,
-        //     &pi as *const mpf_t,
-        //     &e as *const mpf_t,
-        // );
-        println!("computed, making string");
-        let printout = make_cstr_mpf(pi, digits as usize);
-        println!("{printout}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_point_places_the_point() {
+        assert_eq!(with_point("314159", 1), "3.14159");
+        assert_eq!(with_point("1", 2), "10");
+        assert_eq!(with_point("5", 0), "0.5");
+        assert_eq!(with_point("693147", 0), "0.693147");
+        assert_eq!(with_point("1", -2), "0.001");
+        assert_eq!(with_point("25", 2), "25");
+        assert_eq!(with_point("-25", 1), "-2.5");
+        assert_eq!(with_point("", 0), "0");
     }
 }
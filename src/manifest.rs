@@ -0,0 +1,252 @@
+use gmp_mpfr_sys::gmp;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+// filled in from wherever the work happens, read back once the run is done
+static PHASES: Mutex<Vec<(String, f64)>> = Mutex::new(Vec::new());
+static TERMS: AtomicU64 = AtomicU64::new(0);
+
+// phases from concurrent tasks (the series for ln, say) each get their own entry
+pub fn record_phase(name: &str, start: Instant) {
+    let secs = start.elapsed().as_secs_f64();
+    PHASES.lock().unwrap().push((name.to_string(), secs));
+}
+
+// terms summed by binary splitting, over all series in the run
+pub fn record_terms(n: u64) {
+    TERMS.fetch_add(n, Ordering::Relaxed);
+}
+
+// peak resident set size in kB, from VmHWM. None where there is no /proc
+fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub struct Manifest<'a> {
+    pub constant: &'a str,
+    pub algorithm: &'a str,
+    pub digits: u64,
+    pub base: u32,
+    // the value is 0.<digits> * base^exponent
+    pub exponent: i64,
+    pub prec: u64,
+    pub sha256: String,
+    pub merkle_root: Option<String>,
+}
+
+impl Manifest<'_> {
+    pub fn to_json(&self) -> String {
+        let threads = tokio::runtime::Handle::current().metrics().num_workers();
+        let backend = format!(
+            "GMP {}.{}.{}",
+            gmp::VERSION,
+            gmp::VERSION_MINOR,
+            gmp::VERSION_PATCHLEVEL
+        );
+        let phases: Vec<String> = PHASES
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, secs)| format!("{{\"name\": {}, \"seconds\": {secs:.6}}}", json_str(name)))
+            .collect();
+        let peak = peak_memory_kb().map_or("null".to_string(), |kb| kb.to_string());
+        let fields = [
+            ("version", json_str(env!("CARGO_PKG_VERSION"))),
+            ("constant", json_str(self.constant)),
+            ("algorithm", json_str(self.algorithm)),
+            ("digits", self.digits.to_string()),
+            ("base", self.base.to_string()),
+            ("exponent", self.exponent.to_string()),
+            ("precision_bits", self.prec.to_string()),
+            ("terms", TERMS.load(Ordering::Relaxed).to_string()),
+            ("thresh", crate::series::THRESH.to_string()),
+            ("threads", threads.to_string()),
+            ("backend", json_str(&backend)),
+            ("phases", format!("[{}]", phases.join(", "))),
+            ("peak_memory_kb", peak),
+            ("sha256", json_str(&self.sha256)),
+            (
+                "merkle_root",
+                self.merkle_root
+                    .as_deref()
+                    .map_or("null".to_string(), json_str),
+            ),
+        ];
+        let body: Vec<String> = fields
+            .iter()
+            .map(|(k, v)| format!("  {}: {v}", json_str(k)))
+            .collect();
+        format!("{{\n{}\n}}", body.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // just enough of a JSON parser to check the output is well formed
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Null,
+        Num(f64),
+        Str(String),
+        Arr(Vec<Json>),
+        Obj(Vec<(String, Json)>),
+    }
+
+    fn skip_ws(s: &[u8], i: &mut usize) {
+        while s.get(*i).is_some_and(|c| c.is_ascii_whitespace()) {
+            *i += 1;
+        }
+    }
+
+    fn expect(s: &[u8], i: &mut usize, c: u8) {
+        skip_ws(s, i);
+        assert_eq!(s.get(*i), Some(&c), "expected {} at {i}", c as char);
+        *i += 1;
+    }
+
+    // comma separated items up to close, each read by item
+    fn items<T>(
+        s: &[u8],
+        i: &mut usize,
+        close: u8,
+        mut item: impl FnMut(&mut usize) -> T,
+    ) -> Vec<T> {
+        let mut out = Vec::new();
+        skip_ws(s, i);
+        if s[*i] == close {
+            *i += 1;
+            return out;
+        }
+        loop {
+            out.push(item(i));
+            skip_ws(s, i);
+            *i += 1;
+            match s[*i - 1] {
+                b',' => continue,
+                c if c == close => return out,
+                c => panic!("unexpected {} at {}", c as char, *i - 1),
+            }
+        }
+    }
+
+    fn parse_str(s: &[u8], i: &mut usize) -> String {
+        expect(s, i, b'"');
+        let mut out = String::new();
+        while s[*i] != b'"' {
+            if s[*i] == b'\\' {
+                *i += 1;
+            }
+            out.push(s[*i] as char);
+            *i += 1;
+        }
+        *i += 1;
+        out
+    }
+
+    fn parse(s: &[u8], i: &mut usize) -> Json {
+        skip_ws(s, i);
+        match s[*i] {
+            b'{' => {
+                *i += 1;
+                Json::Obj(items(s, i, b'}', |i| {
+                    let key = parse_str(s, i);
+                    expect(s, i, b':');
+                    (key, parse(s, i))
+                }))
+            }
+            b'[' => {
+                *i += 1;
+                Json::Arr(items(s, i, b']', |i| parse(s, i)))
+            }
+            b'"' => Json::Str(parse_str(s, i)),
+            b'n' => {
+                assert_eq!(&s[*i..*i + 4], b"null");
+                *i += 4;
+                Json::Null
+            }
+            _ => {
+                let start = *i;
+                while s
+                    .get(*i)
+                    .is_some_and(|c| b"+-.eE".contains(c) || c.is_ascii_digit())
+                {
+                    *i += 1;
+                }
+                Json::Num(std::str::from_utf8(&s[start..*i]).unwrap().parse().unwrap())
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn manifest_is_json_with_the_documented_fields() {
+        record_phase("compute", Instant::now());
+        record_terms(1234);
+        let json = Manifest {
+            constant: "pi",
+            algorithm: "chudnovsky",
+            digits: 1000,
+            base: 10,
+            exponent: 1,
+            prec: 3386,
+            sha256: "ab\"c".to_string(),
+            merkle_root: Some("0123".to_string()),
+        }
+        .to_json();
+        let (s, mut i) = (json.as_bytes(), 0);
+        let Json::Obj(fields) = parse(s, &mut i) else {
+            panic!("not an object: {json}");
+        };
+        skip_ws(s, &mut i);
+        assert_eq!(i, s.len(), "trailing data in {json}");
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v)
+                .unwrap_or_else(|| panic!("no {name} in {json}"))
+        };
+        assert_eq!(
+            *field("version"),
+            Json::Str(env!("CARGO_PKG_VERSION").to_string())
+        );
+        assert_eq!(*field("constant"), Json::Str("pi".to_string()));
+        assert_eq!(*field("algorithm"), Json::Str("chudnovsky".to_string()));
+        assert_eq!(*field("digits"), Json::Num(1000.0));
+        assert_eq!(*field("base"), Json::Num(10.0));
+        assert_eq!(*field("exponent"), Json::Num(1.0));
+        assert_eq!(*field("precision_bits"), Json::Num(3386.0));
+        assert!(matches!(field("terms"), Json::Num(n) if *n >= 1234.0));
+        assert_eq!(*field("thresh"), Json::Num(crate::series::THRESH as f64));
+        assert!(matches!(field("threads"), Json::Num(n) if *n >= 1.0));
+        assert!(matches!(field("backend"), Json::Str(b) if b.starts_with("GMP ")));
+        let Json::Arr(phases) = field("phases") else {
+            panic!("phases isn't an array");
+        };
+        assert!(phases.iter().any(|p| matches!(p, Json::Obj(f)
+            if f[0] == ("name".to_string(), Json::Str("compute".to_string()))
+                && matches!(f[1], (ref k, Json::Num(_)) if k == "seconds"))));
+        assert!(matches!(field("peak_memory_kb"), Json::Num(_) | Json::Null));
+        assert_eq!(*field("sha256"), Json::Str("ab\"c".to_string()));
+        assert_eq!(*field("merkle_root"), Json::Str("0123".to_string()));
+    }
+}
@@ -0,0 +1,241 @@
+use sha2::{Digest, Sha256};
+
+use crate::flag_value;
+use crate::source::Source;
+
+pub const DEFAULT_BLOCK: usize = 1_000_000;
+
+type Hash = [u8; 32];
+
+fn to_hex(h: &Hash) -> String {
+    h.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Hash {
+    assert!(s.len() == 64, "bad hash {s}");
+    let mut h = [0u8; 32];
+    for (i, b) in h.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).expect("bad hash");
+    }
+    h
+}
+
+// pairs are hashed together level by level. an odd node out is carried up as is
+fn root(leaves: &[Hash]) -> Hash {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [l, r] => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(l);
+                    hasher.update(r);
+                    hasher.finalize().into()
+                }
+                [one] => *one,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+// the tree is stored as its leaves, one hash per block of digits, which is enough to
+// rebuild every node above them:
+//   pi-thing merkle 1
+//   block_size <digits per block>
+//   digits <total digits>
+//   root <hash>
+//   <leaf hash>...
+// returns the root
+pub fn write_tree(path: &str, digits: &[u8], block: usize) -> String {
+    let leaves: Vec<Hash> = digits
+        .chunks(block)
+        .map(|b| Sha256::digest(b).into())
+        .collect();
+    let root = to_hex(&root(&leaves));
+    let mut out = format!(
+        "pi-thing merkle 1\nblock_size {block}\ndigits {}\nroot {root}\n",
+        digits.len()
+    );
+    for leaf in leaves.iter() {
+        out.push_str(&to_hex(leaf));
+        out.push('\n');
+    }
+    std::fs::write(path, out).unwrap();
+    root
+}
+
+struct Tree {
+    block: usize,
+    digits: usize,
+    root: Hash,
+    leaves: Vec<Hash>,
+}
+
+fn read_tree(path: &str) -> Tree {
+    let text = std::fs::read_to_string(path).unwrap();
+    let mut lines = text.lines();
+    assert!(
+        lines.next() == Some("pi-thing merkle 1"),
+        "{path} is not a merkle tree"
+    );
+    let mut field = |name: &str| {
+        let line = lines.next().unwrap_or_default();
+        line.strip_prefix(name)
+            .and_then(|v| v.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("{path}: expected {name}"))
+            .to_string()
+    };
+    let block = field("block_size").parse().unwrap();
+    let digits = field("digits").parse().unwrap();
+    let root = from_hex(&field("root"));
+    let leaves = lines.map(from_hex).collect();
+    Tree {
+        block,
+        digits,
+        root,
+        leaves,
+    }
+}
+
+// the blocks touching digits start..end whose digits don't hash to their leaf. only
+// those blocks are read, through Source so text and packed files hash the same digits
+fn bad_blocks(source: &mut Source, tree: &Tree, start: usize, end: usize) -> Vec<usize> {
+    let mut bad = Vec::new();
+    for i in start / tree.block..end.div_ceil(tree.block) {
+        let b1 = i * tree.block;
+        let b2 = (b1 + tree.block).min(tree.digits);
+        let digits = (b1 as u64 >= source.offset).then(|| source.read_at(b1 as u64, b2 - b1));
+        assert!(
+            digits.as_ref().is_some_and(|d| d.len() == b2 - b1),
+            "the file does not hold all of block {i} (digits {b1}..{b2})"
+        );
+        let h: Hash = Sha256::digest(digits.unwrap()).into();
+        if h != tree.leaves[i] {
+            bad.push(i);
+        }
+    }
+    bad
+}
+
+// pi-thing verify-range <digit file> --tree <merkle file> --start <a> --end <b>
+//                       [--offset <o>]
+// checks digits a..b (0 based, end exclusive) of the full output against the tree. the
+// file may be just a slice of the output starting at digit o, as long as it holds
+// every block the range touches. packed files know their own offset
+pub fn verify_range(args: &[String]) {
+    let path = &args[0];
+    let tree = read_tree(flag_value(args, "--tree").expect("verify-range needs --tree"));
+    let num = |name: &str| flag_value(args, name).map(|v| v.parse::<usize>().unwrap());
+    let start = num("--start").unwrap_or(0);
+    let end = num("--end").unwrap_or(tree.digits);
+    let mut source = Source::open(path);
+    if let Some(offset) = num("--offset") {
+        source.offset = offset as u64;
+    }
+    assert!(start < end && end <= tree.digits, "range outside the tree");
+    assert!(
+        tree.leaves.len() == tree.digits.div_ceil(tree.block),
+        "tree has the wrong number of leaves"
+    );
+
+    let mut ok = root(&tree.leaves) == tree.root;
+    if !ok {
+        println!("leaves do not hash to the root");
+    }
+    println!("root {}", to_hex(&tree.root));
+    for i in bad_blocks(&mut source, &tree, start, end) {
+        let b1 = i * tree.block;
+        let b2 = (b1 + tree.block).min(tree.digits);
+        println!("block {i} (digits {b1}..{b2}) does not match");
+        ok = false;
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+    println!("OK: digits {start}..{end} match the tree");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packed;
+    use crate::temp_path;
+
+    fn digits(n: usize) -> Vec<u8> {
+        (0..n).map(|i| b'0' + (i * 7 % 11 % 10) as u8).collect()
+    }
+
+    #[test]
+    fn root_carries_odd_leaf_up() {
+        let leaves: Vec<Hash> = (0..3u8).map(|i| Sha256::digest([i]).into()).collect();
+        let mut hasher = Sha256::new();
+        hasher.update(leaves[0]);
+        hasher.update(leaves[1]);
+        let left: Hash = hasher.finalize().into();
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(leaves[2]);
+        let expected: Hash = hasher.finalize().into();
+        assert_eq!(root(&leaves), expected);
+        assert_eq!(root(&leaves[..1]), leaves[0]);
+    }
+
+    #[test]
+    fn text_slice_verifies_at_its_offset() {
+        let digits = digits(2500);
+        let (path, tree_path) = (temp_path("merkle.txt"), temp_path("merkle.tree"));
+        let root = write_tree(&tree_path, &digits, 1000);
+        let tree = read_tree(&tree_path);
+        assert_eq!(to_hex(&tree.root), root);
+        std::fs::write(&path, &digits).unwrap();
+        assert!(bad_blocks(&mut Source::open(&path), &tree, 0, 2500).is_empty());
+
+        // a slice holding only the last two blocks
+        std::fs::write(&path, &digits[1000..]).unwrap();
+        let mut source = Source::open(&path);
+        source.offset = 1000;
+        assert!(bad_blocks(&mut source, &tree, 1200, 2500).is_empty());
+
+        let mut corrupt = digits.clone();
+        corrupt[1500] = if corrupt[1500] == b'0' { b'1' } else { b'0' };
+        std::fs::write(&path, &corrupt[1000..]).unwrap();
+        let mut source = Source::open(&path);
+        source.offset = 1000;
+        assert_eq!(bad_blocks(&mut source, &tree, 1000, 2500), [1]);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&tree_path).unwrap();
+    }
+
+    #[test]
+    fn packed_file_verifies_against_its_tree() {
+        let digits = digits(2500);
+        let (path, tree_path) = (temp_path("merkle.pdig"), temp_path("merkle-packed.tree"));
+        let mut header = packed::Header {
+            constant: "test".to_string(),
+            radix: 10,
+            digits: digits.len() as u64,
+            offset: 0,
+            exponent: 1,
+        };
+        packed::write(&path, &header, &digits);
+        write_tree(&tree_path, &digits, 1000);
+        let tree = read_tree(&tree_path);
+        assert!(bad_blocks(&mut Source::open(&path), &tree, 0, 2500).is_empty());
+
+        // a slice holding only the last two blocks, at its offset
+        header.offset = 1000;
+        header.digits = 1500;
+        packed::write(&path, &header, &digits[1000..]);
+        assert!(bad_blocks(&mut Source::open(&path), &tree, 1200, 2500).is_empty());
+
+        let mut corrupt = digits.clone();
+        corrupt[1500] = if corrupt[1500] == b'0' { b'1' } else { b'0' };
+        packed::write(&path, &header, &corrupt[1000..]);
+        assert_eq!(bad_blocks(&mut Source::open(&path), &tree, 1000, 2500), [1]);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&tree_path).unwrap();
+    }
+}
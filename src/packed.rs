@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::flag_value;
+
+// packed digit files: a header, then the digits as little endian u64 words, each
+// holding the value of the next 19 decimal (or 16 hex) digits. the last word holds
+// whatever is left over
+//   magic     8 bytes  "PITHDIG1"
+//   radix     u32      10 or 16
+//   digits    u64      number of digits stored
+//   offset    u64      index of the first stored digit in the full digit string
+//   exponent  i64      the full value is 0.<digits> * radix^exponent
+//   name_len  u32
+//   name      name_len bytes, the constant
+pub const MAGIC: &[u8; 8] = b"PITHDIG1";
+
+pub struct Header {
+    pub constant: String,
+    pub radix: u32,
+    pub digits: u64,
+    pub offset: u64,
+    pub exponent: i64,
+}
+
+fn digits_per_word(radix: u32) -> usize {
+    match radix {
+        10 => 19,
+        16 => 16,
+        _ => panic!("unsupported radix {radix}"),
+    }
+}
+
+impl Header {
+    fn len(&self) -> u64 {
+        8 + 4 + 8 + 8 + 8 + 4 + self.constant.len() as u64
+    }
+}
+
+// digits are ascii, as printed
+pub fn write(path: &str, header: &Header, digits: &[u8]) {
+    assert!(header.digits == digits.len() as u64);
+    let mut out = BufWriter::new(File::create(path).unwrap());
+    out.write_all(MAGIC).unwrap();
+    out.write_all(&header.radix.to_le_bytes()).unwrap();
+    out.write_all(&header.digits.to_le_bytes()).unwrap();
+    out.write_all(&header.offset.to_le_bytes()).unwrap();
+    out.write_all(&header.exponent.to_le_bytes()).unwrap();
+    out.write_all(&(header.constant.len() as u32).to_le_bytes())
+        .unwrap();
+    out.write_all(header.constant.as_bytes()).unwrap();
+    for chunk in digits.chunks(digits_per_word(header.radix)) {
+        let word = chunk.iter().fold(0u64, |acc, &d| {
+            let d = (d as char)
+                .to_digit(header.radix)
+                .unwrap_or_else(|| panic!("bad digit {}", d as char));
+            acc * header.radix as u64 + d as u64
+        });
+        out.write_all(&word.to_le_bytes()).unwrap();
+    }
+    out.flush().unwrap();
+}
+
+pub struct Reader {
+    file: File,
+    pub header: Header,
+}
+
+impl Reader {
+    pub fn open(path: &str) -> Reader {
+        let mut file = File::open(path).unwrap();
+        let mut buf = [0u8; 40];
+        file.read_exact(&mut buf).unwrap();
+        assert!(&buf[..8] == MAGIC, "{path} is not a packed digit file");
+        let u32_at = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(buf[i..i + 8].try_into().unwrap());
+        let (radix, digits, offset) = (u32_at(8), u64_at(12), u64_at(20));
+        let exponent = u64_at(28) as i64;
+        let mut name = vec![0u8; u32_at(36) as usize];
+        file.read_exact(&mut name).unwrap();
+        // rejects radixes this can't read
+        digits_per_word(radix);
+        Reader {
+            file,
+            header: Header {
+                constant: String::from_utf8(name).unwrap(),
+                radix,
+                digits,
+                offset,
+                exponent,
+            },
+        }
+    }
+
+    // stored digits start..end, counted from the start of the file (not the offset)
+    pub fn read_range(&mut self, start: u64, end: u64) -> String {
+        assert!(
+            start <= end && end <= self.header.digits,
+            "range outside the file"
+        );
+        let per_word = digits_per_word(self.header.radix) as u64;
+        let (w1, w2) = (start / per_word, end.div_ceil(per_word));
+        self.file
+            .seek(SeekFrom::Start(self.header.len() + 8 * w1))
+            .unwrap();
+        let mut raw = vec![0u8; 8 * (w2 - w1) as usize];
+        self.file.read_exact(&mut raw).unwrap();
+        let mut text = String::with_capacity(((w2 - w1) * per_word) as usize);
+        for (i, bytes) in raw.chunks(8).enumerate() {
+            let word = u64::from_le_bytes(bytes.try_into().unwrap());
+            // the last word of the file can be short
+            let width = per_word.min(self.header.digits - (w1 + i as u64) * per_word) as usize;
+            if self.header.radix == 10 {
+                text.push_str(&format!("{word:0width$}"));
+            } else {
+                text.push_str(&format!("{word:0width$x}"));
+            }
+        }
+        text[(start - w1 * per_word) as usize..(end - w1 * per_word) as usize].to_string()
+    }
+}
+
+// pi-thing pack <text file> --output <file> [--constant <name>] [--offset <o>]
+//                [--exponent <e>] [--hex]
+// the exponent defaults to 1, digits that start in the units place like pi's
+pub fn pack(args: &[String]) {
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let digits = text.trim().as_bytes();
+    let header = Header {
+        constant: flag_value(args, "--constant").unwrap_or("pi").to_string(),
+        radix: if args.iter().any(|a| a == "--hex") {
+            16
+        } else {
+            10
+        },
+        digits: digits.len() as u64,
+        offset: flag_value(args, "--offset").map_or(0, |o| o.parse().unwrap()),
+        exponent: flag_value(args, "--exponent").map_or(1, |e| e.parse().unwrap()),
+    };
+    let path = flag_value(args, "--output").expect("pack needs --output");
+    write(path, &header, digits);
+    println!("{} digits packed into {path}", header.digits);
+}
+
+// pi-thing unpack <file> [--start <a>] [--end <b>] [--output <text file>]
+// start and end are positions in the full digit string, so they take the offset into
+// account
+pub fn unpack(args: &[String]) {
+    let mut reader = Reader::open(&args[0]);
+    let h = &reader.header;
+    println!(
+        "{}: {} base {} digits from {}, exponent {}",
+        h.constant, h.digits, h.radix, h.offset, h.exponent
+    );
+    let (first, last) = (h.offset, h.offset + h.digits);
+    let num = |name: &str| flag_value(args, name).map(|v| v.parse::<u64>().unwrap());
+    let start = num("--start").unwrap_or(first);
+    let end = num("--end").unwrap_or(last);
+    assert!(
+        first <= start && end <= last,
+        "the file holds digits {first}..{last}"
+    );
+    let text = reader.read_range(start - first, end - first);
+    match flag_value(args, "--output") {
+        Some(path) => std::fs::write(path, text).unwrap(),
+        None => println!("{text}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_path;
+
+    #[test]
+    fn round_trip_keeps_header_and_digits() {
+        for (radix, alphabet) in [(10, &b"0123456789"[..]), (16, &b"0123456789abcdef"[..])] {
+            for n in [0, 1, 16, 19, 20, 1000] {
+                let digits: Vec<u8> = (0..n).map(|i| alphabet[i * 7 % alphabet.len()]).collect();
+                let path = temp_path(&format!("packed-{radix}-{n}.pdig"));
+                let header = Header {
+                    constant: "sqrt2".to_string(),
+                    radix,
+                    digits: n as u64,
+                    offset: 12345,
+                    exponent: -3,
+                };
+                write(&path, &header, &digits);
+                let mut reader = Reader::open(&path);
+                assert_eq!(reader.header.constant, "sqrt2");
+                assert_eq!(reader.header.radix, radix);
+                assert_eq!(reader.header.digits, n as u64);
+                assert_eq!(reader.header.offset, 12345);
+                assert_eq!(reader.header.exponent, -3);
+                assert_eq!(reader.header.len(), header.len());
+                assert_eq!(reader.read_range(0, n as u64).as_bytes(), &digits[..]);
+                // ranges starting and ending inside words
+                for (a, b) in [(0, 1), (3, 18), (18, 20), (19, 38), (5, n), (n, n)] {
+                    if a <= b && b <= n {
+                        let range = reader.read_range(a as u64, b as u64);
+                        assert_eq!(range.as_bytes(), &digits[a..b]);
+                    }
+                }
+                std::fs::remove_file(&path).unwrap();
+            }
+        }
+    }
+}
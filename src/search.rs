@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+use crate::source::{CHUNK, Source};
+use crate::{compute, flag_value, make_cstr_mpf, prec_for_digits};
+
+// chunks being scanned at once, which bounds memory to about this many chunks
+pub const IN_FLIGHT: usize = 64;
+
+// positions (relative to the block) of every match that lies entirely in the block
+fn scan(block: &[u8], pattern: &[u8]) -> Vec<usize> {
+    block
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, w)| *w == pattern)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// count and the first `keep` positions of pattern in the digits next_chunk hands
+// out, which start at position start. each chunk is scanned in its own task, with the
+// last pattern.len() - 1 digits of the one before it in front, so a match across a
+// chunk boundary is found exactly once
+pub async fn search_stream(
+    mut next_chunk: impl FnMut(&mut Vec<u8>),
+    start: u64,
+    pattern: &[u8],
+    keep: usize,
+) -> (u64, Vec<u64>) {
+    let pattern = Arc::new(pattern.to_vec());
+    let mut pending: VecDeque<JoinHandle<Vec<u64>>> = VecDeque::new();
+    let (mut count, mut found) = (0u64, Vec::new());
+    let mut collect = |positions: Vec<u64>| {
+        count += positions.len() as u64;
+        found.extend(positions.into_iter().take(keep - found.len().min(keep)));
+    };
+    let mut carry: Vec<u8> = Vec::new();
+    let mut pos = start;
+    let mut buf = Vec::new();
+    loop {
+        next_chunk(&mut buf);
+        if buf.is_empty() {
+            break;
+        }
+        let mut block = std::mem::take(&mut carry);
+        block.extend_from_slice(&buf);
+        carry = block[block.len().saturating_sub(pattern.len() - 1)..].to_vec();
+        let base = pos;
+        pos += (block.len() - carry.len()) as u64;
+        let pattern = pattern.clone();
+        pending.push_back(tokio::spawn(async move {
+            scan(&block, &pattern)
+                .into_iter()
+                .map(|i| base + i as u64)
+                .collect()
+        }));
+        if pending.len() >= IN_FLIGHT {
+            collect(pending.pop_front().unwrap().await.unwrap());
+        }
+    }
+    for handle in pending {
+        collect(handle.await.unwrap());
+    }
+    (count, found)
+}
+
+// pi-thing search <pattern> [--file <file> | --digits <n> [--constant <name>]]
+//                  [--max-results <k>]
+// positions count from the first digit of the output, so for pi position i is
+// decimal place i
+pub async fn run(args: &[String]) {
+    let pattern = args[0].as_bytes();
+    assert!(!pattern.is_empty(), "empty pattern");
+    let keep = flag_value(args, "--max-results").map_or(20, |k| k.parse::<usize>().unwrap());
+    let (count, found) = match flag_value(args, "--file") {
+        Some(path) => {
+            let mut source = Source::open(path);
+            let start = source.offset;
+            search_stream(|buf| source.read_chunk(buf, CHUNK), start, pattern, keep).await
+        }
+        None => {
+            let digits = flag_value(args, "--digits")
+                .expect("search needs --file or --digits")
+                .parse::<u64>()
+                .unwrap();
+            let constant = flag_value(args, "--constant").unwrap_or("pi");
+            println!("Computing {digits} digits of {constant}");
+            let value = compute(constant, "chudnovsky", digits, prec_for_digits(digits)).await;
+            let printout = make_cstr_mpf(value, digits as usize);
+            // the rounded last digit is left out
+            let mut chunks = printout.as_bytes()[..printout.len() - 1].chunks(CHUNK);
+            let next = |buf: &mut Vec<u8>| {
+                buf.clear();
+                buf.extend_from_slice(chunks.next().unwrap_or_default());
+            };
+            search_stream(next, 0, pattern, keep).await
+        }
+    };
+    println!("{count} occurrences of {}", args[0]);
+    for p in found.iter() {
+        println!("{p}");
+    }
+    if count > found.len() as u64 {
+        println!("... {} more", count - found.len() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn matches_across_chunks_are_found_once() {
+        let digits: Vec<u8> = (0..5000u64)
+            .map(|i| b'0' + (i * i % 13 % 3) as u8)
+            .collect();
+        for pattern in [&b"0"[..], b"01", b"0120", b"1111", b"2102010"] {
+            let expected = scan(&digits, pattern);
+            // chunks shorter than the pattern, and more of them than IN_FLIGHT
+            for size in [1, 3, 7, 64, 5000] {
+                let mut chunks = digits.chunks(size);
+                let next = |buf: &mut Vec<u8>| {
+                    buf.clear();
+                    buf.extend_from_slice(chunks.next().unwrap_or_default());
+                };
+                let (count, found) = search_stream(next, 100, pattern, 10).await;
+                assert_eq!(
+                    count,
+                    expected.len() as u64,
+                    "{pattern:?} in chunks of {size}"
+                );
+                let first: Vec<u64> = expected.iter().take(10).map(|&i| 100 + i as u64).collect();
+                assert_eq!(found, first);
+            }
+        }
+    }
+}
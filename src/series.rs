@@ -0,0 +1,768 @@
+use gmp_mpfr_sys::{gmp, gmp::mpz_t};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::fac::{Fac, Sieve};
+use crate::manifest;
+use crate::modarith::{mul_mod, pow_mod};
+use crate::{WrappedMpf, WrappedMpz, WrappedMpzTri, allocate_mpf, allocate_mpz, mpf_cast};
+
+pub const THRESH: u64 = 10u64.pow(4) * 5;
+// ranges up to this many terms keep P and Q in factored form as well, so common
+// factors can be cancelled when merging
+const FAC_THRESH: u64 = 1 << 12;
+// ranges up to this many terms are computed directly instead of split further
+pub const LEAF_TERMS: u64 = 32;
+// with checks on, P, Q and T are also tracked mod these primes and every merge is
+// checked against them. a subtree that fails is recomputed up to CHECK_ATTEMPTS times
+const CHECK_PRIMES: [u64; 2] = [(1 << 63) - 25, u64::MAX - 58];
+const CHECK_ATTEMPTS: u32 = 3;
+static CHECKS: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_checks() {
+    CHECKS.store(true, Ordering::Relaxed);
+}
+
+// [P, Q, T] mod each of CHECK_PRIMES
+type Residues = [[u64; 3]; CHECK_PRIMES.len()];
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct PQT {
+    pub p: gmp::mpz_t,
+    pub q: gmp::mpz_t,
+    pub t: gmp::mpz_t,
+    fp: Option<Fac>,
+    fq: Option<Fac>,
+    res: Option<Residues>,
+}
+
+unsafe impl Send for PQT {} // luckily, im not accessing anything between threads
+
+// a polynomial in k, constant coefficient first
+pub struct Poly {
+    c: Vec<i64>,
+}
+
+impl Poly {
+    pub fn new(c: &[i64]) -> Poly {
+        Poly { c: c.to_vec() }
+    }
+
+    fn eval(&self, k: u64) -> i128 {
+        self.c
+            .iter()
+            .rev()
+            .fold(0i128, |acc, &c| acc * k as i128 + c as i128)
+    }
+
+    fn degree(&self) -> usize {
+        self.c.len().saturating_sub(1)
+    }
+
+    fn lead(&self) -> i64 {
+        *self.c.last().unwrap_or(&0)
+    }
+}
+
+// a hypergeometric series sum_{k >= 0} a(k) * prod_{j = 1..=k} p(j) / q(j).
+// p and q are products of small polynomials, each of which has to be nonzero and
+// fit in a word for k >= 1, so leaves can use word arithmetic and the sieve
+pub trait Series: Send + Sync {
+    fn p(&self) -> Vec<Poly>;
+    fn q(&self) -> Vec<Poly>;
+    fn a(&self) -> Poly;
+}
+
+// a series prepared for summing. constant factors are split out of p and q so they
+// only have to be factored once instead of at every leaf
+pub struct Terms {
+    p_var: Vec<Poly>,
+    q_var: Vec<Poly>,
+    p_const: Vec<i64>,
+    q_const: Vec<i64>,
+    a: Poly,
+    p_const_fac: Fac,
+    q_const_fac: Fac,
+    sieve: Sieve,
+    // sum of log2 |leading coefficient| and of the degrees of all factors of p and q
+    lead_bits: f64,
+    degree: f64,
+    check: bool,
+}
+
+impl Terms {
+    // n is the number of terms that will be summed, which bounds what the sieve needs
+    pub fn new(series: &dyn Series, n: u64) -> Terms {
+        let (p, q) = (series.p(), series.q());
+        let lead_bits = p
+            .iter()
+            .chain(q.iter())
+            .map(|f| (f.lead().unsigned_abs() as f64).log2())
+            .sum();
+        let degree = p.iter().chain(q.iter()).map(|f| f.degree()).sum::<usize>() as f64;
+        let (p_const, p_var): (Vec<Poly>, Vec<Poly>) = p.into_iter().partition(|f| f.degree() == 0);
+        let (q_const, q_var): (Vec<Poly>, Vec<Poly>) = q.into_iter().partition(|f| f.degree() == 0);
+        let p_const: Vec<i64> = p_const.iter().map(|f| f.lead()).collect();
+        let q_const: Vec<i64> = q_const.iter().map(|f| f.lead()).collect();
+        let const_fac = |c: &[i64]| {
+            c.iter().fold(Fac::default(), |f, &v| {
+                f.mul(&Fac::from_const(v.unsigned_abs()))
+            })
+        };
+        let bound = p_var
+            .iter()
+            .chain(q_var.iter())
+            .map(|f| f.eval(n.max(1)).unsigned_abs() as u64)
+            .max()
+            .unwrap_or(0);
+        Terms {
+            p_const_fac: const_fac(&p_const),
+            q_const_fac: const_fac(&q_const),
+            p_var,
+            q_var,
+            p_const,
+            q_const,
+            a: series.a(),
+            sieve: Sieve::new(bound + 1),
+            lead_bits,
+            degree,
+            check: CHECKS.load(Ordering::Relaxed),
+        }
+    }
+
+    // estimated size in bits of P(0, n) * Q(0, n): sum of log2 |p(k) q(k)| for k <= n,
+    // taking only the leading terms of the factors and stirling for log2(n!)
+    fn est_bits(&self, n: u64) -> f64 {
+        if n == 0 {
+            return 0f64;
+        }
+        let n = n as f64;
+        let ln_fact = n * n.ln() - n + 0.5 * (2f64 * std::f64::consts::PI * n).ln();
+        n * self.lead_bits + self.degree * ln_fact / 2f64.ln()
+    }
+
+    // later terms are bigger, so the midpoint would leave the right half with more work.
+    // split where the estimated operand sizes of both halves are equal instead
+    fn split_point(&self, n1: u64, n2: u64) -> u64 {
+        let half = (self.est_bits(n1) + self.est_bits(n2)) / 2f64;
+        let (mut lo, mut hi) = (n1 + 1, n2 - 1);
+        while lo < hi {
+            let m = lo + (hi - lo) / 2;
+            if self.est_bits(m) < half {
+                lo = m + 1;
+            } else {
+                hi = m;
+            }
+        }
+        lo
+    }
+}
+
+// number of terms after which the tail of the series is below 10^-digits, estimated
+// from the leading coefficients of p and q like est_bits, with a few terms to spare
+pub fn terms_for_digits(series: &dyn Series, digits: u64) -> u64 {
+    let (p, q) = (series.p(), series.q());
+    let lead = |fs: &[Poly]| {
+        fs.iter()
+            .map(|f| (f.lead().unsigned_abs() as f64).log10())
+            .sum::<f64>()
+    };
+    let degree = |fs: &[Poly]| fs.iter().map(|f| f.degree()).sum::<usize>() as f64;
+    let per_term = lead(&q) - lead(&p);
+    let degree = degree(&q) - degree(&p);
+    assert!(degree > 0f64 || per_term > 0f64, "series does not converge");
+    let decimals = |n: u64| {
+        let n = n as f64;
+        let log10_fact = if n < 1f64 {
+            0f64
+        } else {
+            (n * n.ln() - n + 0.5 * (2f64 * std::f64::consts::PI * n).ln()) / 10f64.ln()
+        };
+        n * per_term + degree * log10_fact
+    };
+    let target = digits as f64 + 4f64;
+    let mut hi = 1;
+    while decimals(hi) < target {
+        hi *= 2;
+    }
+    let mut lo = 0;
+    while lo < hi {
+        let m = lo + (hi - lo) / 2;
+        if decimals(m) < target {
+            lo = m + 1;
+        } else {
+            hi = m;
+        }
+    }
+    lo + 2
+}
+
+// z = v
+unsafe fn mpz_set_u128(z: *mut mpz_t, v: u128) {
+    unsafe {
+        gmp::mpz_set_ui(z, (v >> 64) as u64);
+        gmp::mpz_mul_2exp(z, z, 64);
+        gmp::mpz_add_ui(z, z, v as u64);
+    }
+}
+
+// z *= v, with tmp as scratch space for values that don't fit in a limb
+pub unsafe fn mpz_mul_u128(z: *mut mpz_t, v: u128, tmp: *mut mpz_t) {
+    unsafe {
+        if v <= u64::MAX as u128 {
+            gmp::mpz_mul_ui(z, z, v as u64);
+        } else {
+            mpz_set_u128(tmp, v);
+            gmp::mpz_mul(z, z, tmp);
+        }
+    }
+}
+
+// z *= f(k) for every factor, multiplied together in a u128 as far as it fits.
+// the magnitudes of the non constant factors are pushed to vals for factoring later.
+// returns whether the product was negative
+unsafe fn mpz_mul_factors(
+    z: *mut mpz_t,
+    consts: &[i64],
+    var: &[Poly],
+    k: u64,
+    vals: &mut Vec<u64>,
+    tmp: *mut mpz_t,
+) -> bool {
+    let mut acc: u128 = 1;
+    let mut neg = false;
+    let start = vals.len();
+    vals.extend(var.iter().map(|f| {
+        let v = f.eval(k);
+        neg ^= v < 0;
+        u64::try_from(v.unsigned_abs()).expect("series factor does not fit in a word")
+    }));
+    unsafe {
+        for v in consts
+            .iter()
+            .map(|&c| {
+                neg ^= c < 0;
+                c.unsigned_abs()
+            })
+            .chain(vals[start..].iter().copied())
+        {
+            acc = match acc.checked_mul(v as u128) {
+                Some(acc) => acc,
+                None => {
+                    mpz_mul_u128(z, acc, tmp);
+                    v as u128
+                }
+            };
+        }
+        mpz_mul_u128(z, acc, tmp);
+        if neg {
+            gmp::mpz_neg(z, z);
+        }
+    }
+    neg
+}
+
+// terms n1 + 1 ..= n2 appended one at a time: P *= p(k), T = T * q(k) + a(k) * P.
+// p(k) and q(k) are built in words, and the non constant part of Q is multiplied
+// up in a u128 over several terms before it touches GMP at all
+unsafe fn leaf_pqt(terms: &Terms, pqt: &mut PQT, n1: u64, n2: u64) {
+    unsafe {
+        let mut tmp = allocate_mpz(0);
+        let p = &mut pqt.p as *mut mpz_t;
+        let q = &mut pqt.q as *mut mpz_t;
+        let t = &mut pqt.t as *mut mpz_t;
+        let tmp_p = &mut tmp as *mut mpz_t;
+        gmp::mpz_set_ui(p, 1);
+        gmp::mpz_set_ui(q, 1);
+        gmp::mpz_set_ui(t, 0);
+        let len = n2 - n1;
+        let mut q_acc: u128 = 1;
+        let mut q_neg = false;
+        let mut p_vals = Vec::with_capacity(terms.p_var.len() * len as usize);
+        let mut q_vals = Vec::with_capacity(terms.q_var.len() * len as usize);
+        for k in n1 + 1..=n2 {
+            mpz_mul_factors(p, &terms.p_const, &terms.p_var, k, &mut p_vals, tmp_p);
+            let q_start = q_vals.len();
+            q_neg ^= mpz_mul_factors(t, &terms.q_const, &terms.q_var, k, &mut q_vals, tmp_p);
+            let a = terms.a.eval(k);
+            if a.unsigned_abs() <= u64::MAX as u128 {
+                if a < 0 {
+                    gmp::mpz_submul_ui(t, p, a.unsigned_abs() as u64);
+                } else {
+                    gmp::mpz_addmul_ui(t, p, a as u64);
+                }
+            } else {
+                mpz_set_u128(tmp_p, a.unsigned_abs());
+                if a < 0 {
+                    gmp::mpz_submul(t, p, tmp_p);
+                } else {
+                    gmp::mpz_addmul(t, p, tmp_p);
+                }
+            }
+            for &v in q_vals[q_start..].iter() {
+                q_acc = match q_acc.checked_mul(v as u128) {
+                    Some(acc) => acc,
+                    None => {
+                        mpz_mul_u128(q, q_acc, tmp_p);
+                        v as u128
+                    }
+                };
+            }
+        }
+        mpz_mul_u128(q, q_acc, tmp_p);
+        for &c in terms.q_const.iter() {
+            gmp::mpz_ui_pow_ui(tmp_p, c.unsigned_abs(), len);
+            gmp::mpz_mul(q, q, tmp_p);
+        }
+        if q_neg {
+            gmp::mpz_neg(q, q);
+        }
+        gmp::mpz_clear(tmp_p);
+        pqt.fp = Some(
+            Fac::from_values(&p_vals, &terms.sieve).mul(&terms.p_const_fac.clone().pow(len as u32)),
+        );
+        pqt.fq = Some(
+            Fac::from_values(&q_vals, &terms.sieve).mul(&terms.q_const_fac.clone().pow(len as u32)),
+        );
+    }
+}
+
+fn add_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 + b as u128) % m as u128) as u64
+}
+
+fn reduce(v: i128, m: u64) -> u64 {
+    v.rem_euclid(m as i128) as u64
+}
+
+// the leaf recurrence again, but mod each check prime and straight from p, q and a,
+// so it shares nothing with the GMP path it checks
+fn leaf_residues(terms: &Terms, n1: u64, n2: u64) -> Residues {
+    let mut res = [[1, 1, 0]; CHECK_PRIMES.len()];
+    for (r, &m) in res.iter_mut().zip(CHECK_PRIMES.iter()) {
+        let product = |consts: &[i64], var: &[Poly], k: u64| {
+            consts
+                .iter()
+                .map(|&c| c as i128)
+                .chain(var.iter().map(|f| f.eval(k)))
+                .fold(1, |acc, v| mul_mod(acc, reduce(v, m), m))
+        };
+        for k in n1 + 1..=n2 {
+            let p = product(&terms.p_const, &terms.p_var, k);
+            let q = product(&terms.q_const, &terms.q_var, k);
+            r[0] = mul_mod(r[0], p, m);
+            r[1] = mul_mod(r[1], q, m);
+            r[2] = add_mod(
+                mul_mod(r[2], q, m),
+                mul_mod(reduce(terms.a.eval(k), m), r[0], m),
+                m,
+            );
+        }
+    }
+    res
+}
+
+fn merge_residues(res1: &PQT, res2: &PQT) -> Option<Residues> {
+    let (Some(r1), Some(r2)) = (res1.res, res2.res) else {
+        return None;
+    };
+    let mut res = [[0; 3]; CHECK_PRIMES.len()];
+    for (i, &m) in CHECK_PRIMES.iter().enumerate() {
+        let ([p1, q1, t1], [p2, q2, t2]) = (r1[i], r2[i]);
+        res[i] = [
+            mul_mod(p1, p2, m),
+            mul_mod(q1, q2, m),
+            add_mod(mul_mod(t1, q2, m), mul_mod(p1, t2, m), m),
+        ];
+    }
+    Some(res)
+}
+
+// whether P, Q and T reduce to the tracked residues. P is only there to check when
+// it was needed
+unsafe fn residues_match(pqt: &PQT, need_p: bool) -> bool {
+    let Some(res) = pqt.res else {
+        return true;
+    };
+    res.iter().zip(CHECK_PRIMES).all(|(r, m)| unsafe {
+        (!need_p || gmp::mpz_fdiv_ui(&pqt.p, m) == r[0])
+            && gmp::mpz_fdiv_ui(&pqt.q, m) == r[1]
+            && gmp::mpz_fdiv_ui(&pqt.t, m) == r[2]
+    })
+}
+
+// reports a failed check and frees the bad result so the range can be redone
+unsafe fn check_failed(pqt: &mut PQT, n1: u64, n2: u64, attempt: u32) {
+    eprintln!(
+        "check failed for terms {}..={} (attempt {attempt}), recomputing",
+        n1 + 1,
+        n2
+    );
+    assert!(
+        attempt < CHECK_ATTEMPTS,
+        "terms {}..={} failed {CHECK_ATTEMPTS} checks, giving up",
+        n1 + 1,
+        n2
+    );
+    unsafe {
+        gmp::mpz_clear(&mut pqt.p);
+        gmp::mpz_clear(&mut pqt.q);
+        gmp::mpz_clear(&mut pqt.t);
+    }
+}
+
+// cancels g = gcd(P1, Q2) out of both children. g divides P, Q and T of the merged
+// range alike (T = T1 * Q2 + P1 * T2), so T / Q and everything above is unchanged
+unsafe fn remove_common_factors(res1: &mut PQT, res2: &mut PQT) {
+    let (Some(fp), Some(fq)) = (res1.fp.as_mut(), res2.fq.as_mut()) else {
+        return;
+    };
+    let g = fp.gcd(fq);
+    if g.is_empty() {
+        return;
+    }
+    unsafe {
+        let mut g_z = g.to_mpz();
+        gmp::mpz_divexact(
+            &mut res1.p as *mut mpz_t,
+            &res1.p as *const mpz_t,
+            &g_z as *const mpz_t,
+        );
+        gmp::mpz_divexact(
+            &mut res2.q as *mut mpz_t,
+            &res2.q as *const mpz_t,
+            &g_z as *const mpz_t,
+        );
+        gmp::mpz_clear(&mut g_z);
+    }
+    fp.div(&g);
+    fq.div(&g);
+    if let (Some(r1), Some(r2)) = (res1.res.as_mut(), res2.res.as_mut()) {
+        for (i, &m) in CHECK_PRIMES.iter().enumerate() {
+            // the primes are far above any factor, so g is invertible
+            let g_inv = pow_mod(g.rem(m), m - 2, m);
+            r1[i][0] = mul_mod(r1[i][0], g_inv, m);
+            r2[i][1] = mul_mod(r2[i][1], g_inv, m);
+        }
+    }
+}
+
+fn merge_facs(res1: &PQT, res2: &PQT, len: u64, need_p: bool) -> (Option<Fac>, Option<Fac>) {
+    if len > FAC_THRESH {
+        return (None, None);
+    }
+    let fp = match (&res1.fp, &res2.fp) {
+        (Some(fp1), Some(fp2)) if need_p => Some(fp1.mul(fp2)),
+        _ => None,
+    };
+    let fq = match (&res1.fq, &res2.fq) {
+        (Some(fq1), Some(fq2)) => Some(fq1.mul(fq2)),
+        _ => None,
+    };
+    (fp, fq)
+}
+
+fn i_compute_pqt(terms: &Terms, n1: u64, n2: u64, need_p: bool) -> PQT {
+    let mut attempt = 1;
+    loop {
+        let mut pqt = i_compute_pqt_once(terms, n1, n2, need_p);
+        unsafe {
+            if residues_match(&pqt, need_p) {
+                return pqt;
+            }
+            check_failed(&mut pqt, n1, n2, attempt);
+        }
+        attempt += 1;
+    }
+}
+
+fn i_compute_pqt_once(terms: &Terms, n1: u64, n2: u64, need_p: bool) -> PQT {
+    let mut pqt: PQT = PQT {
+        p: allocate_mpz(0),
+        q: allocate_mpz(0),
+        t: allocate_mpz(0),
+        fp: None,
+        fq: None,
+        res: None,
+    };
+    unsafe {
+        if n2 - n1 <= LEAF_TERMS {
+            leaf_pqt(terms, &mut pqt, n1, n2);
+            if terms.check {
+                pqt.res = Some(leaf_residues(terms, n1, n2));
+            }
+        } else {
+            let m = terms.split_point(n1, n2);
+            // single thread
+            let mut res1 = i_compute_pqt(terms, n1, m, true); // res1 is used as a temp buffer to reduce mem
+            let mut res2 = i_compute_pqt(terms, m, n2, need_p);
+            remove_common_factors(&mut res1, &mut res2);
+            (pqt.fp, pqt.fq) = merge_facs(&res1, &res2, n2 - n1, need_p);
+            pqt.res = merge_residues(&res1, &res2);
+            if need_p {
+                gmp::mpz_mul(
+                    &mut pqt.p as *mut mpz_t,
+                    &res1.p as *const mpz_t,
+                    &res2.p as *const mpz_t,
+                );
+            }
+            gmp::mpz_mul(
+                &mut pqt.q as *mut mpz_t,
+                &res1.q as *const mpz_t,
+                &res2.q as *const mpz_t,
+            );
+            let mut t_1 = allocate_mpz(0);
+            let mut t_2 = allocate_mpz(0);
+            gmp::mpz_mul(
+                &mut t_1 as *mut mpz_t,
+                &res1.t as *const mpz_t,
+                &res2.q as *const mpz_t,
+            );
+            gmp::mpz_mul(
+                &mut t_2 as *mut mpz_t,
+                &res1.p as *const mpz_t,
+                &res2.t as *const mpz_t,
+            );
+            gmp::mpz_add(
+                &mut pqt.t as *mut mpz_t,
+                &t_1 as *const mpz_t,
+                &t_2 as *const mpz_t,
+            );
+
+            gmp::mpz_clear(&mut res1.p);
+            gmp::mpz_clear(&mut res1.q);
+            gmp::mpz_clear(&mut res1.t);
+            gmp::mpz_clear(&mut res2.p);
+            gmp::mpz_clear(&mut res2.q);
+            gmp::mpz_clear(&mut res2.t);
+            gmp::mpz_clear(&mut t_1);
+            gmp::mpz_clear(&mut t_2);
+        }
+    }
+    pqt
+}
+
+// need_p is false along the rightmost path of the tree, where P is never read again
+// (only the left child's P feeds into T), so the biggest multiplications can be skipped
+async fn compute_pqt(terms: Arc<Terms>, n1: u64, n2: u64, need_p: bool) -> PQT {
+    if n2 - n1 <= LEAF_TERMS {
+        return i_compute_pqt(&terms, n1, n2, need_p);
+    }
+    let mut attempt = 1;
+    loop {
+        let mut pqt = compute_pqt_once(terms.clone(), n1, n2, need_p).await;
+        unsafe {
+            if residues_match(&pqt, need_p) {
+                return pqt;
+            }
+            check_failed(&mut pqt, n1, n2, attempt);
+        }
+        attempt += 1;
+    }
+}
+
+#[async_recursion::async_recursion]
+async fn compute_pqt_once(terms: Arc<Terms>, n1: u64, n2: u64, need_p: bool) -> PQT {
+    let mut pqt: PQT = PQT {
+        p: allocate_mpz(0),
+        q: allocate_mpz(0),
+        t: allocate_mpz(0),
+        fp: None,
+        fq: None,
+        res: None,
+    };
+    let t_1 = WrappedMpz { a: allocate_mpz(0) };
+    let t_2 = WrappedMpz { a: allocate_mpz(0) };
+    unsafe {
+        let m = terms.split_point(n1, n2);
+        let mut res1: PQT;
+        let mut res2: PQT;
+        if n2 - n1 < THRESH {
+            res1 = i_compute_pqt(&terms, n1, m, true);
+            res2 = i_compute_pqt(&terms, m, n2, need_p);
+        } else {
+            let res1_hook = tokio::spawn(compute_pqt(terms.clone(), n1, m, true));
+            let res2_hook = tokio::spawn(compute_pqt(terms.clone(), m, n2, need_p));
+            res1 = res1_hook.await.unwrap();
+            res2 = res2_hook.await.unwrap();
+        }
+        if n2 - n1 > THRESH {
+            // left / right operand sizes, to keep an eye on how balanced the split is
+            eprintln!(
+                "{} {}/{} bits",
+                n2 - n1,
+                gmp::mpz_sizeinbase(&res1.q as *const mpz_t, 2),
+                gmp::mpz_sizeinbase(&res2.q as *const mpz_t, 2)
+            );
+        }
+        remove_common_factors(&mut res1, &mut res2);
+        (pqt.fp, pqt.fq) = merge_facs(&res1, &res2, n2 - n1, need_p);
+        pqt.res = merge_residues(&res1, &res2);
+        // p = res1 p * res2 p
+        let wrap_p = WrappedMpzTri {
+            a: pqt.p,
+            b: res1.p,
+            c: res2.p,
+        };
+        let p_thread = need_p.then(|| {
+            tokio::spawn(async move {
+                let mut wrap = wrap_p;
+                gmp::mpz_mul(
+                    &mut wrap.a as *mut mpz_t,
+                    &wrap.b as *const mpz_t,
+                    &wrap.c as *const mpz_t,
+                );
+                WrappedMpz { a: wrap.a }
+            })
+        });
+        // q = res1 q * res2 q
+        let wrap_q = WrappedMpzTri {
+            a: pqt.q,
+            b: res1.q,
+            c: res2.q,
+        };
+        let q_thread = tokio::spawn(async move {
+            let mut wrap = wrap_q;
+            gmp::mpz_mul(
+                &mut wrap.a as *mut mpz_t,
+                &wrap.b as *const mpz_t,
+                &wrap.c as *const mpz_t,
+            );
+            WrappedMpz { a: wrap.a }
+        });
+
+        let t_1_wrap = WrappedMpzTri {
+            a: t_1.a,
+            b: res1.t,
+            c: res2.q,
+        };
+        let t_1_handle = tokio::spawn(async move {
+            let mut wrap = t_1_wrap;
+            gmp::mpz_mul(
+                &mut wrap.a as *mut mpz_t,
+                &wrap.b as *const mpz_t,
+                &wrap.c as *const mpz_t,
+            );
+            WrappedMpz { a: wrap.a }
+        });
+
+        let t_2_wrap = WrappedMpzTri {
+            a: t_2.a,
+            b: res1.p,
+            c: res2.t,
+        };
+        let t_2_handle = tokio::spawn(async move {
+            let mut wrap = t_2_wrap;
+            gmp::mpz_mul(
+                &mut wrap.a as *mut mpz_t,
+                &wrap.b as *const mpz_t,
+                &wrap.c as *const mpz_t,
+            );
+            WrappedMpz { a: wrap.a }
+        });
+        let mut t_1 = t_1_handle.await.unwrap();
+        let mut t_2 = t_2_handle.await.unwrap();
+        gmp::mpz_add(
+            &mut pqt.t as *mut mpz_t,
+            &t_1.a as *const mpz_t,
+            &t_2.a as *const mpz_t,
+        );
+        if let Some(p_thread) = p_thread {
+            pqt.p = p_thread.await.unwrap().a;
+        }
+        pqt.q = q_thread.await.unwrap().a;
+        gmp::mpz_clear(&mut res1.p);
+        gmp::mpz_clear(&mut res1.q);
+        gmp::mpz_clear(&mut res1.t);
+        gmp::mpz_clear(&mut res2.p);
+        gmp::mpz_clear(&mut res2.q);
+        gmp::mpz_clear(&mut res2.t);
+        gmp::mpz_clear(&mut t_1.a);
+        gmp::mpz_clear(&mut t_2.a);
+    }
+    pqt
+}
+
+// P, Q and T of terms 1..=n, so the series is a(0) + T / Q
+pub async fn sum_series(series: &dyn Series, n: u64) -> PQT {
+    let start = Instant::now();
+    let terms = Arc::new(Terms::new(series, n));
+    let pqt = compute_pqt(terms, 0, n, false).await;
+    manifest::record_terms(n);
+    manifest::record_phase("binary splitting", start);
+    pqt
+}
+
+// a(0) + T / Q over the first n terms, to prec bits
+pub async fn eval_series(series: &dyn Series, n: u64, prec: u64) -> WrappedMpf {
+    let mut pqt = sum_series(series, n).await;
+    eprintln!("pqt done");
+    unsafe {
+        let a0 = series.a().eval(0);
+        {
+            let mut tmp = allocate_mpz(0);
+            mpz_set_u128(&mut tmp as *mut mpz_t, a0.unsigned_abs());
+            if a0 < 0 {
+                gmp::mpz_submul(&mut pqt.t, &pqt.q, &tmp);
+            } else {
+                gmp::mpz_addmul(&mut pqt.t, &pqt.q, &tmp);
+            }
+            gmp::mpz_clear(&mut tmp);
+        }
+        gmp::mpz_clear(&mut pqt.p);
+        let q_handle = tokio::spawn(mpf_cast(WrappedMpz { a: pqt.q }, prec));
+        let t_handle = tokio::spawn(mpf_cast(WrappedMpz { a: pqt.t }, prec));
+        let (q, t) = tokio::join!(q_handle, t_handle);
+        let (mut q, mut t) = (q.unwrap().a, t.unwrap().a);
+        gmp::mpz_clear(&mut pqt.q);
+        gmp::mpz_clear(&mut pqt.t);
+        let mut value = allocate_mpf(0, prec);
+        gmp::mpf_div(&mut value, &t, &q);
+        gmp::mpf_clear(&mut q);
+        gmp::mpf_clear(&mut t);
+        WrappedMpf { a: value }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chudnovsky::Chudnovsky;
+
+    fn checked_pqt(n: u64) -> PQT {
+        let mut terms = Terms::new(&Chudnovsky, n);
+        terms.check = true;
+        i_compute_pqt_once(&terms, 0, n, true)
+    }
+
+    #[test]
+    fn corrupted_merges_fail_the_check() {
+        let mut pqt = checked_pqt(200);
+        assert!(pqt.res.is_some());
+        unsafe {
+            assert!(residues_match(&pqt, true));
+            for z in [&raw mut pqt.p, &raw mut pqt.q, &raw mut pqt.t] {
+                gmp::mpz_add_ui(z, z, 1);
+                let bad = !residues_match(&pqt, true);
+                gmp::mpz_sub_ui(z, z, 1);
+                assert!(bad);
+            }
+            assert!(residues_match(&pqt, true));
+            gmp::mpz_clear(&mut pqt.p);
+            gmp::mpz_clear(&mut pqt.q);
+            gmp::mpz_clear(&mut pqt.t);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed 3 checks, giving up")]
+    fn check_failed_gives_up_after_the_last_attempt() {
+        let mut pqt = checked_pqt(100);
+        unsafe {
+            check_failed(&mut pqt, 0, 100, 1);
+            pqt = checked_pqt(100);
+            check_failed(&mut pqt, 0, 100, CHECK_ATTEMPTS);
+        }
+    }
+}
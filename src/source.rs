@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use crate::packed::{MAGIC, Reader};
+
+// digits read per call when a caller has no reason to pick something else
+pub const CHUNK: usize = 1 << 20;
+// digits between the byte positions read_at remembers for text files with line breaks
+const STRIDE: u64 = 4096;
+
+enum Kind {
+    // marks is filled in by the first read_at
+    Text {
+        file: BufReader<File>,
+        marks: Option<Vec<u64>>,
+    },
+    Packed {
+        reader: Reader,
+        pos: u64,
+    },
+}
+
+// byte positions of digits 0, STRIDE, 2 STRIDE, ... of a text file, or nothing when
+// the file is digits only and every digit's byte position is the digit position
+fn digit_marks(file: &mut BufReader<File>) -> Vec<u64> {
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let (mut marks, mut digits, mut byte, mut gaps) = (Vec::new(), 0u64, 0u64, false);
+    let mut raw = vec![0u8; CHUNK];
+    loop {
+        let n = file.read(&mut raw).unwrap();
+        if n == 0 {
+            break;
+        }
+        for &b in raw[..n].iter() {
+            if b.is_ascii_whitespace() {
+                gaps = true;
+            } else {
+                if digits.is_multiple_of(STRIDE) {
+                    marks.push(byte);
+                }
+                digits += 1;
+            }
+            byte += 1;
+        }
+    }
+    if !gaps {
+        marks.clear();
+    }
+    marks
+}
+
+// a digit file, text or packed, read either by digit position or front to back a
+// chunk at a time so memory stays bounded whatever the file size. whitespace in text
+// files is skipped
+pub struct Source {
+    kind: Kind,
+    // position of the first digit in the full digit string
+    pub offset: u64,
+}
+
+impl Source {
+    pub fn open(path: &str) -> Source {
+        let mut file = File::open(path).unwrap();
+        let mut magic = [0u8; 8];
+        let is_packed = file.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+        if is_packed {
+            let reader = Reader::open(path);
+            Source {
+                offset: reader.header.offset,
+                kind: Kind::Packed { reader, pos: 0 },
+            }
+        } else {
+            Source {
+                kind: Kind::Text {
+                    file: BufReader::new(File::open(path).unwrap()),
+                    marks: None,
+                },
+                offset: 0,
+            }
+        }
+    }
+
+    // replaces buf with up to max more digits. empty at the end of the file
+    pub fn read_chunk(&mut self, buf: &mut Vec<u8>, max: usize) {
+        buf.clear();
+        match &mut self.kind {
+            Kind::Text { file, .. } => {
+                let mut raw = vec![0u8; max];
+                while buf.len() < max {
+                    let n = file.read(&mut raw[..max - buf.len()]).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend(raw[..n].iter().filter(|b| !b.is_ascii_whitespace()));
+                }
+            }
+            Kind::Packed { reader, pos } => {
+                let end = (*pos + max as u64).min(reader.header.digits);
+                buf.extend_from_slice(reader.read_range(*pos, end).as_bytes());
+                *pos = end;
+            }
+        }
+    }
+
+    // len digits from position pos of the full digit string, or fewer at the end of
+    // the file. a text file with whitespace in it is read through once on the first
+    // call, to find where every STRIDE-th digit is. moves the text read position, so
+    // don't mix with read_chunk
+    pub fn read_at(&mut self, pos: u64, len: usize) -> Vec<u8> {
+        assert!(pos >= self.offset, "position before the start of the file");
+        let rel = pos - self.offset;
+        match &mut self.kind {
+            Kind::Text { file, marks } => {
+                let marks = marks.get_or_insert_with(|| digit_marks(file));
+                let mut buf = Vec::with_capacity(len);
+                if marks.is_empty() {
+                    file.seek(SeekFrom::Start(rel)).unwrap();
+                    file.take(len as u64).read_to_end(&mut buf).unwrap();
+                    return buf;
+                }
+                let Some(&byte) = marks.get((rel / STRIDE) as usize) else {
+                    return buf;
+                };
+                file.seek(SeekFrom::Start(byte)).unwrap();
+                let mut skip = rel % STRIDE;
+                let mut raw = [0u8; 4096];
+                while buf.len() < len {
+                    let n = file.read(&mut raw).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    for &b in raw[..n].iter().filter(|b| !b.is_ascii_whitespace()) {
+                        if skip > 0 {
+                            skip -= 1;
+                        } else if buf.len() < len {
+                            buf.push(b);
+                        }
+                    }
+                }
+                buf
+            }
+            Kind::Packed { reader, .. } => {
+                let start = rel.min(reader.header.digits);
+                let end = (start + len as u64).min(reader.header.digits);
+                reader.read_range(start, end).into_bytes()
+            }
+        }
+    }
+
+    // drops the next n digits
+    pub fn skip(&mut self, mut n: u64) {
+        let mut buf = Vec::new();
+        while n > 0 {
+            self.read_chunk(&mut buf, (n as usize).min(CHUNK));
+            if buf.is_empty() {
+                return;
+            }
+            n -= buf.len() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_path;
+
+    #[test]
+    fn wrapped_text_reads_by_digit_position() {
+        let digits: Vec<u8> = (0..3 * STRIDE as usize + 500)
+            .map(|i| b'0' + (i * 7 % 10) as u8)
+            .collect();
+        let wrapped: Vec<u8> = digits
+            .chunks(70)
+            .flat_map(|line| line.iter().copied().chain(*b"\r\n"))
+            .collect();
+        let path = temp_path("source-wrapped.txt");
+        for text in [&digits, &wrapped] {
+            std::fs::write(&path, text).unwrap();
+            let mut source = Source::open(&path);
+            source.offset = 100;
+            for (pos, len) in [(100, 10), (169, 3), (100 + STRIDE, 70), (2 * STRIDE, 5000)] {
+                let rel = (pos - 100) as usize;
+                let want = &digits[rel..(rel + len).min(digits.len())];
+                assert_eq!(source.read_at(pos, len), want, "{pos}");
+            }
+            assert!(source.read_at(100 + digits.len() as u64, 10).is_empty());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
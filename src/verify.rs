@@ -0,0 +1,88 @@
+use std::time::Instant;
+
+use crate::{compute, flag_value, make_cstr_mpf, prec_for_digits};
+
+// digits past the ones compared, so a run of 9s or 0s at the end can't make two
+// correct results round differently
+const GUARD_DIGITS: u64 = 10;
+
+// pi with chudnovsky and an independent formula, printed to digits + GUARD_DIGITS and
+// compared digit by digit. agm is the default second algorithm as it is the only one
+// that doesn't go through the binary splitting engine chudnovsky uses, so a bug there
+// can't break both results the same way
+pub async fn run(args: &[String]) {
+    let digits = flag_value(args, "--digits")
+        .expect("verify needs --digits")
+        .parse::<u64>()
+        .unwrap();
+    let second = flag_value(args, "--algorithm").unwrap_or("agm");
+    assert!(
+        second != "chudnovsky",
+        "verify needs an algorithm other than chudnovsky"
+    );
+    let prec = prec_for_digits(digits + GUARD_DIGITS);
+    let total = Instant::now();
+    let mut results = Vec::new();
+    for algorithm in ["chudnovsky", second] {
+        println!("Computing {digits} digits of pi with {algorithm}");
+        let start = Instant::now();
+        let value = compute("pi", algorithm, digits + GUARD_DIGITS, prec).await;
+        let printout = make_cstr_mpf(value, (digits + GUARD_DIGITS) as usize);
+        let elapsed = start.elapsed();
+        println!("{algorithm} done in {:.3}s", elapsed.as_secs_f64());
+        results.push((algorithm, printout, elapsed));
+    }
+
+    println!("digits: {digits}");
+    println!("precision: {prec} bits");
+    let threads = tokio::runtime::Handle::current().metrics().num_workers();
+    println!("threads: {threads}");
+    if second != "agm" {
+        println!("note: {second} shares the binary splitting engine with chudnovsky");
+    }
+    for (algorithm, _, elapsed) in results.iter() {
+        println!("{algorithm}: {:.3}s", elapsed.as_secs_f64());
+    }
+    println!("total: {:.3}s", total.elapsed().as_secs_f64());
+    let (a, b) = (&results[0].1, &results[1].1);
+    match first_mismatch(a, b, digits as usize) {
+        // the leading 3 is index 0, so the index is also the decimal place
+        Some(i) => {
+            println!(
+                "MISMATCH at decimal place {i}: chudnovsky {} vs {second} {}",
+                context(a, i),
+                context(b, i)
+            );
+            std::process::exit(1);
+        }
+        None => println!("OK: {digits} digits agree"),
+    }
+}
+
+// mpf_get_str drops trailing zeros, so a digit past the end of the string is a 0
+fn digit(s: &str, i: usize) -> u8 {
+    s.as_bytes().get(i).copied().unwrap_or(b'0')
+}
+
+fn first_mismatch(a: &str, b: &str, digits: usize) -> Option<usize> {
+    (0..digits).find(|&i| digit(a, i) != digit(b, i))
+}
+
+fn context(s: &str, i: usize) -> String {
+    (i..i + 10).map(|j| digit(s, j) as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_trailing_zeros_still_agree() {
+        assert_eq!(first_mismatch("314159", "314159", 6), None);
+        assert_eq!(first_mismatch("25", "25000", 5), None);
+        assert_eq!(first_mismatch("25", "25001", 5), Some(4));
+        assert_eq!(first_mismatch("25", "25001", 4), None);
+        assert_eq!(first_mismatch("31415", "31416", 5), Some(4));
+        assert_eq!(context("31415", 3), "1500000000");
+    }
+}